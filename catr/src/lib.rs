@@ -1,25 +1,95 @@
 use std::error::Error;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
 
 use clap::{Arg, ArgAction, Command};
+use regex::Regex;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+const COLOR_MATCH: &str = "\x1b[1;31m";
+const COLOR_RESET: &str = "\x1b[0m";
+
 #[derive(Debug)]
 pub struct Config {
-    files: Vec<String>,
-    number_lines: bool,
-    number_nonblank_lines: bool,
+    pub files: Vec<String>,
+    pub number_lines: bool,
+    pub number_nonblank_lines: bool,
+    pub checksum: bool,
+    pub highlight: Option<Regex>,
+    pub color: bool,
+    pub squeeze_blank: bool,
+    pub squeeze_report: bool,
+    pub show_ends: bool,
+    pub show_tabs: bool,
+    pub show_nonprinting: bool,
+    pub number_start: usize,
+    pub number_width: usize,
+}
+
+impl Config {
+    // ライブラリとして使う場合の入口。オプションは既定値なので必要ならフィールドを直接変更する
+    pub fn new(files: Vec<String>) -> Config {
+        Config {
+            files,
+            number_lines: false,
+            number_nonblank_lines: false,
+            checksum: false,
+            highlight: None,
+            color: false,
+            squeeze_blank: false,
+            squeeze_report: false,
+            show_ends: false,
+            show_tabs: false,
+            show_nonprinting: false,
+            number_start: 1,
+            number_width: 6,
+        }
+    }
+}
+
+// 読み込んだ生のバイト列から CRC32 を計算するためのラッパー
+struct ChecksumReader<R> {
+    inner: R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes = self.inner.read(buf)?;
+        self.hasher.update(&buf[..bytes]);
+        Ok(bytes)
+    }
 }
 
 pub fn run(config: Config) -> MyResult<()> {
+    run_with_writer(&config, &mut io::stdout().lock())
+}
+
+pub fn run_with_writer(config: &Config, out: &mut impl Write) -> MyResult<()> {
+    let mut squeezed = 0;
     for filename in &config.files {
-        match open(&filename) {
+        match open(filename) {
             Err(e) => eprintln!("Failed to open {}: {}", filename, e),
-            Ok(reader) => print_lines(reader, &config),
+            Ok(reader) if config.checksum => {
+                let mut reader = ChecksumReader {
+                    inner: reader,
+                    hasher: crc32fast::Hasher::new(),
+                };
+                squeezed += print_lines(BufReader::new(&mut reader), config, out)?;
+                writeln!(
+                    out,
+                    "# crc32 {:08x}  {}",
+                    reader.hasher.finalize(),
+                    filename
+                )?;
+            }
+            Ok(reader) => squeezed += print_lines(reader, config, out)?,
         }
     }
+    if config.squeeze_report {
+        eprintln!("catr: squeezed {} blank line(s)", squeezed);
+    }
     Ok(())
 }
 
@@ -50,8 +120,97 @@ pub fn get_args() -> MyResult<Config> {
                 .action(ArgAction::SetTrue)
                 .help("output non-blank line numbers"),
         )
+        .arg(
+            Arg::new("number_start")
+                .value_name("N")
+                .long("number-start")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize))
+                .default_value("1")
+                .help("number lines starting from N"),
+        )
+        .arg(
+            Arg::new("number_width")
+                .value_name("W")
+                .long("number-width")
+                .num_args(1)
+                .value_parser(clap::value_parser!(u16).range(1..))
+                .default_value("6")
+                .help("pad line numbers to W columns"),
+        )
+        .arg(
+            Arg::new("checksum")
+                .long("checksum")
+                .action(ArgAction::SetTrue)
+                .help("print a CRC32 checksum line after each file"),
+        )
+        .arg(
+            Arg::new("highlight")
+                .value_name("PATTERN")
+                .long("highlight")
+                .num_args(1)
+                .value_parser(|s: &str| Regex::new(s))
+                .help("highlight matches of PATTERN"),
+        )
+        .arg(
+            Arg::new("color")
+                .value_name("WHEN")
+                .long("color")
+                .num_args(1)
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto")
+                .help("when to use colors for --highlight"),
+        )
+        .arg(
+            Arg::new("squeeze_blank")
+                .short('s')
+                .long("squeeze-blank")
+                .action(ArgAction::SetTrue)
+                .help("suppress repeated empty output lines"),
+        )
+        .arg(
+            Arg::new("squeeze_report")
+                .long("squeeze-report")
+                .action(ArgAction::SetTrue)
+                .requires("squeeze_blank")
+                .help("print the number of suppressed empty lines to stderr"),
+        )
+        .arg(
+            Arg::new("show_ends")
+                .short('E')
+                .long("show-ends")
+                .action(ArgAction::SetTrue)
+                .help("display $ at end of each line"),
+        )
+        .arg(
+            Arg::new("show_tabs")
+                .short('T')
+                .long("show-tabs")
+                .action(ArgAction::SetTrue)
+                .help("display TAB characters as ^I"),
+        )
+        .arg(
+            Arg::new("show_nonprinting")
+                .short('v')
+                .long("show-nonprinting")
+                .action(ArgAction::SetTrue)
+                .help("use ^ and M- notation, except for LFD and TAB"),
+        )
+        .arg(
+            Arg::new("show_all")
+                .short('A')
+                .long("show-all")
+                .action(ArgAction::SetTrue)
+                .help("equivalent to -vET"),
+        )
         .get_matches();
 
+    let color = match matches.get_one::<String>("color").unwrap().as_str() {
+        "always" => true,
+        "never" => false,
+        _ => io::stdout().is_terminal(),
+    };
+
     Ok(Config {
         files: matches
             .get_many::<String>("files")
@@ -60,31 +219,116 @@ pub fn get_args() -> MyResult<Config> {
             .collect::<Vec<String>>(),
         number_lines: matches.get_flag("number"),
         number_nonblank_lines: matches.get_flag("number_nonblank"),
+        checksum: matches.get_flag("checksum"),
+        highlight: matches.get_one::<Regex>("highlight").cloned(),
+        color,
+        squeeze_blank: matches.get_flag("squeeze_blank"),
+        squeeze_report: matches.get_flag("squeeze_report"),
+        show_ends: matches.get_flag("show_ends") || matches.get_flag("show_all"),
+        show_tabs: matches.get_flag("show_tabs") || matches.get_flag("show_all"),
+        show_nonprinting: matches.get_flag("show_nonprinting") || matches.get_flag("show_all"),
+        number_start: *matches.get_one::<usize>("number_start").unwrap(),
+        number_width: *matches.get_one::<u16>("number_width").unwrap() as usize,
     })
 }
 
-fn print_lines(reader: Box<dyn BufRead>, config: &Config) {
-    let mut line_number = 0;
-    for line in reader.lines() {
-        match line {
-            Ok(line) => {
-                if config.number_nonblank_lines {
-                    if !line.is_empty() {
-                        line_number += 1;
-                        print!("{:6}\t", line_number);
-                    }
-                    println!("{}", line);
-                } else if config.number_lines {
-                    line_number += 1;
-                    println!("{:6}\t{}", line_number, line);
-                } else {
-                    line_number += 1;
-                    println!("{}", line);
-                }
+// -s で省いた空行の数を返す。行は改行 (と最後の行に改行がないこと) も含めてそのまま出す
+fn print_lines(mut reader: impl BufRead, config: &Config, out: &mut impl Write) -> MyResult<usize> {
+    let mut line_number = config.number_start;
+    let mut squeezed = 0;
+    let mut last_blank = false;
+    loop {
+        let mut line = vec![];
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                break;
             }
-            Err(e) => eprintln!("Error: {}", e),
         }
+        let newline = line.last() == Some(&b'\n');
+        if newline {
+            line.pop();
+        }
+
+        // 空行が続く場合は最初の 1 行だけ出す
+        let blank = line.is_empty();
+        if config.squeeze_blank && blank && last_blank {
+            squeezed += 1;
+            continue;
+        }
+        last_blank = blank;
+
+        // 色付けのエスケープまで ^[ にしないよう、先に見える形にする
+        if config.show_tabs || config.show_nonprinting {
+            line = make_visible(&line, config.show_tabs, config.show_nonprinting);
+        }
+        if let Some(pattern) = config.highlight.as_ref().filter(|_| config.color) {
+            line = highlight(&String::from_utf8_lossy(&line), pattern).into_bytes();
+        }
+        // GNU cat と同じく、$ は改行の前にだけ付ける
+        if newline {
+            if config.show_ends {
+                line.push(b'$');
+            }
+            line.push(b'\n');
+        }
+
+        // -E で $ が付いても空行には番号を付けない
+        if config.number_lines || (config.number_nonblank_lines && !blank) {
+            write!(out, "{:width$}\t", line_number, width = config.number_width)?;
+            line_number = line_number.saturating_add(1);
+        }
+        out.write_all(&line)?;
     }
+    Ok(squeezed)
+}
+
+// -T ならタブを ^I に、-v なら制御文字を ^X に、上位ビットの立ったバイトを M- に変える
+fn make_visible(line: &[u8], show_tabs: bool, show_nonprinting: bool) -> Vec<u8> {
+    let mut result = Vec::with_capacity(line.len());
+    for &byte in line {
+        if byte == b'\t' {
+            if show_tabs {
+                result.extend_from_slice(b"^I");
+            } else {
+                result.push(byte);
+            }
+            continue;
+        }
+        if !show_nonprinting {
+            result.push(byte);
+            continue;
+        }
+        let byte = if byte >= 0x80 {
+            result.extend_from_slice(b"M-");
+            byte - 0x80
+        } else {
+            byte
+        };
+        match byte {
+            0x7f => result.extend_from_slice(b"^?"),
+            0x00..=0x1f => result.extend_from_slice(&[b'^', byte + 0x40]),
+            _ => result.push(byte),
+        }
+    }
+    result
+}
+
+fn highlight(line: &str, pattern: &Regex) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+    // 空文字へのマッチは色付けしない
+    for m in pattern.find_iter(line).filter(|m| !m.is_empty()) {
+        result.push_str(&line[last..m.start()]);
+        result.push_str(COLOR_MATCH);
+        result.push_str(m.as_str());
+        result.push_str(COLOR_RESET);
+        last = m.end();
+    }
+    result.push_str(&line[last..]);
+    result
 }
 
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
@@ -93,3 +337,38 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
         _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{make_visible, run_with_writer, Config};
+
+    #[test]
+    fn test_make_visible() {
+        // 制御文字
+        assert_eq!(make_visible(b"\x00\x01\x1b\r", false, true), b"^@^A^[^M");
+        assert_eq!(make_visible(b"a\x7fb", false, true), b"a^?b");
+        // 上位ビットの立ったバイト
+        assert_eq!(
+            make_visible(b"\x80\x89\xa0\xe9", false, true),
+            b"M-^@M-^IM- M-i"
+        );
+        assert_eq!(make_visible(b"\xff", false, true), b"M-^?");
+        // タブは -T のときだけ変える
+        assert_eq!(make_visible(b"a\tb", false, true), b"a\tb");
+        assert_eq!(make_visible(b"a\tb", true, true), b"a^Ib");
+        assert_eq!(make_visible(b"\x01\tb", true, false), b"\x01^Ib");
+    }
+
+    #[test]
+    fn test_run_with_writer() {
+        let mut config = Config::new(vec!["tests/inputs/blanks.txt".to_string()]);
+        config.number_lines = true;
+        config.squeeze_blank = true;
+        let mut out = vec![];
+        run_with_writer(&config, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "     1\tfirst\n     2\t\n     3\tsecond\n"
+        );
+    }
+}
@@ -10,6 +10,8 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const SPIDERS: &str = "tests/inputs/spiders.txt";
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+const BLANKS: &str = "tests/inputs/blanks.txt";
+const NO_NEWLINE: &str = "tests/inputs/no-newline.txt";
 
 // --------------------------------------------------
 #[test]
@@ -197,3 +199,209 @@ fn all_n() -> Result<()> {
 fn all_b() -> Result<()> {
     run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn fox_checksum() -> Result<()> {
+    let expected = format!(
+        "{}# crc32 eb50cc6a  {}\n",
+        fs::read_to_string("tests/expected/fox.txt.out")?,
+        FOX
+    );
+    Command::cargo_bin(PRG)?
+        .args(["--checksum", FOX])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn empty_checksum() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--checksum", EMPTY])
+        .assert()
+        .success()
+        .stdout(format!("# crc32 00000000  {}\n", EMPTY));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn highlight_always() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--highlight", "o+", "--color", "always", "-n"])
+        .write_stdin("foo bar\nbaz\n\nboo\n")
+        .assert()
+        .success()
+        .stdout(
+            "     1\tf\x1b[1;31moo\x1b[0m bar\n     2\tbaz\n     3\t\n     4\tb\x1b[1;31moo\x1b[0m\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn highlight_not_tty() -> Result<()> {
+    // 端末でなければ色を付けない
+    run(&["--highlight", "fox", FOX], "tests/expected/fox.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn squeeze_blank() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-s", BLANKS])
+        .assert()
+        .success()
+        .stdout("first\n\nsecond\n");
+    // 残った空行にも番号を付ける
+    Command::cargo_bin(PRG)?
+        .args(["-s", "-n", BLANKS])
+        .assert()
+        .success()
+        .stdout("     1\tfirst\n     2\t\n     3\tsecond\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn show_ends_tabs() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-E"])
+        .write_stdin("a\tb\n\nc\n")
+        .assert()
+        .success()
+        .stdout("a\tb$\n$\nc$\n");
+    Command::cargo_bin(PRG)?
+        .args(["-T"])
+        .write_stdin("a\tb\n\nc\n")
+        .assert()
+        .success()
+        .stdout("a^Ib\n\nc\n");
+    Command::cargo_bin(PRG)?
+        .args(["-A", "-b"])
+        .write_stdin("a\tb\n\nc\n")
+        .assert()
+        .success()
+        .stdout("     1\ta^Ib$\n$\n     2\tc$\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn keeps_line_endings() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .arg(NO_NEWLINE)
+        .assert()
+        .success()
+        .stdout("one\r\ntwo\nthree");
+    Command::cargo_bin(PRG)?
+        .args(["-n", NO_NEWLINE])
+        .assert()
+        .success()
+        .stdout("     1\tone\r\n     2\ttwo\n     3\tthree");
+    Command::cargo_bin(PRG)?
+        .args(["-E", NO_NEWLINE])
+        .assert()
+        .success()
+        .stdout("one\r$\ntwo$\nthree");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn show_nonprinting() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .arg("-v")
+        .write_stdin(&b"caf\xc3\xa9\t\x1b[0m\r\n"[..])
+        .assert()
+        .success()
+        .stdout("cafM-CM-)\t^[[0m^M\n");
+    Command::cargo_bin(PRG)?
+        .arg("-A")
+        .write_stdin(&b"a\tb\r\n"[..])
+        .assert()
+        .success()
+        .stdout("a^Ib^M$\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn number_start_width() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--number-start", "100", "--number-width", "3"])
+        .write_stdin("a\n\nb\n")
+        .assert()
+        .success()
+        .stdout("100\ta\n101\t\n102\tb\n");
+    // 幅より大きい番号は切り詰めない
+    Command::cargo_bin(PRG)?
+        .args(["-b", "--number-start", "99", "--number-width", "1"])
+        .write_stdin("a\n\nb\n")
+        .assert()
+        .success()
+        .stdout("99\ta\n\n100\tb\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn number_start_max() -> Result<()> {
+    // 最大値からは増やせないので同じ番号のまま
+    let max = usize::MAX.to_string();
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--number-start", &max])
+        .write_stdin("a\nb\n")
+        .assert()
+        .success()
+        .stdout(format!("{max}\ta\n{max}\tb\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_number_width() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--number-width", "0"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn squeeze_report() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-s", "--squeeze-report", "-n"])
+        .write_stdin("a\n\n\n\nb\n\nc\n\n\n")
+        .assert()
+        .success()
+        .stdout("     1\ta\n     2\t\n     3\tb\n     4\t\n     5\tc\n     6\t\n")
+        .stderr("catr: squeezed 3 blank line(s)\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_squeeze_report_without_s() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--squeeze-report", FOX])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn library_run_with_writer() -> Result<()> {
+    let mut config = catr::Config::new(vec!["tests/inputs/no-newline.txt".to_string()]);
+    config.show_ends = true;
+    let mut out = vec![];
+    // 他のクレートからも Config を組み立てて出力先を渡せる
+    catr::run_with_writer(&config, &mut out).unwrap();
+    assert_eq!(out, b"one\r$\ntwo$\nthree");
+    Ok(())
+}
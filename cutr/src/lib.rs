@@ -1,16 +1,17 @@
 use crate::Extract::*;
-use clap::{Arg, Command};
-use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use clap::{Arg, ArgAction, Command};
+use csv::{ErrorKind, ReaderBuilder, StringRecord, Terminator, Writer, WriterBuilder};
 use regex::Regex;
 use std::{
     error::Error,
     fs::File,
-    io::{self, BufRead, BufReader},
+    io::{self, BufRead, BufReader, Write},
     ops::Range,
     vec,
 };
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
+// 終端のない範囲は end を usize::MAX で表す
 type PositionList = Vec<Range<usize>>;
 
 #[derive(Debug)]
@@ -22,43 +23,265 @@ pub enum Extract {
 
 #[derive(Debug)]
 pub struct Config {
-    files: Vec<String>,
-    delimiter: u8,
-    extract: Extract,
+    pub files: Vec<String>,
+    pub delimiter: u8,
+    pub whitespace: bool,
+    pub output_delimiter: Option<u8>,
+    pub extract: Extract,
+    pub pad_missing: bool,
+    pub byte_align: bool,
+    pub only_delimited: bool,
+    pub transpose: bool,
+    pub sum: bool,
+    pub strict_sum: bool,
+    pub zero_terminated: bool,
+    pub names: Option<Vec<String>>,
+    pub validate: bool,
+}
+
+impl Config {
+    // ライブラリとして使う場合の入口。オプションは既定値なので必要ならフィールドを直接変更する
+    pub fn new(files: Vec<String>, delimiter: u8, extract: Extract) -> Config {
+        Config {
+            files,
+            delimiter,
+            whitespace: false,
+            output_delimiter: None,
+            extract,
+            pad_missing: false,
+            byte_align: false,
+            only_delimited: false,
+            transpose: false,
+            sum: false,
+            strict_sum: false,
+            zero_terminated: false,
+            names: None,
+            validate: false,
+        }
+    }
+}
+
+// -w の出力は csv の規則 (クォートや空レコードの "") を使わず単純につなぐ
+enum RowWriter<W: Write> {
+    Csv(Box<Writer<W>>),
+    Plain {
+        out: W,
+        delimiter: u8,
+        terminator: u8,
+    },
+}
+
+impl<W: Write> RowWriter<W> {
+    fn write_row<I, T>(&mut self, row: I) -> MyResult<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        match self {
+            RowWriter::Csv(writer) => writer.write_record(row)?,
+            RowWriter::Plain {
+                out,
+                delimiter,
+                terminator,
+            } => {
+                for (i, field) in row.into_iter().enumerate() {
+                    if i > 0 {
+                        out.write_all(&[*delimiter])?;
+                    }
+                    out.write_all(field.as_ref())?;
+                }
+                out.write_all(&[*terminator])?;
+            }
+        }
+        Ok(())
+    }
 }
 
 pub fn run(config: Config) -> MyResult<()> {
+    run_with_writer(&config, &mut io::stdout())
+}
+
+pub fn run_with_writer(config: &Config, out: &mut impl Write) -> MyResult<()> {
+    let output_delimiter = match config.output_delimiter {
+        Some(delimiter) => delimiter,
+        None if config.whitespace => b' ',
+        None => config.delimiter,
+    };
+    let terminator = if config.zero_terminated { b'\0' } else { b'\n' };
+    let mut sums: Vec<f64> = vec![];
+    let mut invalid = 0;
     for filename in &config.files {
         match open(filename) {
             Err(err) => eprintln!("{}: {}", filename, err),
+            Ok(file) if config.validate => {
+                invalid += validate(file, filename, config.delimiter, terminator)?;
+            }
             Ok(file) => match &config.extract {
                 Fields(field_pos) => {
-                    let mut reader = ReaderBuilder::new()
-                        .delimiter(config.delimiter)
-                        .has_headers(false)
-                        .from_reader(file);
-                    let mut writer = WriterBuilder::new()
-                        .delimiter(config.delimiter)
-                        .from_writer(io::stdout());
-
-                    for record in reader.records() {
+                    let records: Box<dyn Iterator<Item = MyResult<StringRecord>>> =
+                        if config.whitespace {
+                            // csv は区切り文字ごとに分割してしまうので、空白の連続は自前で分割する
+                            Box::new(read_lines(file, config.zero_terminated).map(|line| {
+                                Ok(StringRecord::from(
+                                    line?.split_whitespace().collect::<Vec<_>>(),
+                                ))
+                            }))
+                        } else {
+                            let reader = ReaderBuilder::new()
+                                .delimiter(config.delimiter)
+                                .has_headers(false)
+                                .flexible(true)
+                                .terminator(Terminator::Any(terminator))
+                                .from_reader(file);
+                            Box::new(reader.into_records().map(|record| Ok(record?)))
+                        };
+                    let mut writer = if config.whitespace {
+                        RowWriter::Plain {
+                            out: &mut *out,
+                            delimiter: output_delimiter,
+                            terminator,
+                        }
+                    } else {
+                        RowWriter::Csv(Box::new(
+                            WriterBuilder::new()
+                                .delimiter(output_delimiter)
+                                .flexible(true)
+                                .terminator(Terminator::Any(terminator))
+                                .from_writer(&mut *out),
+                        ))
+                    };
+                    // 列名の数と合わないレコードがあればファイルごとに一度だけ警告する
+                    let mut warned = false;
+                    let records = records.inspect(|record| {
+                        if let (Some(names), Ok(record)) = (&config.names, record) {
+                            if !warned && record.len() != names.len() {
+                                eprintln!(
+                                    "{}: record has {} fields but {} names were given",
+                                    filename,
+                                    record.len(),
+                                    names.len()
+                                );
+                                warned = true;
+                            }
+                        }
+                    });
+                    // csv で分割済みなので、フィールドが 1 つだけのレコードを
+                    // 区切り文字を含まない行とみなす
+                    let records = records.filter(|record| {
+                        !config.only_delimited || record.as_ref().map_or(true, |r| r.len() > 1)
+                    });
+
+                    if config.transpose {
+                        let mut rows = vec![];
+                        for record in records {
+                            let record = record?;
+                            // フィールド指定がなければ全フィールドを使う
+                            rows.push(if field_pos.is_empty() {
+                                record.iter().map(String::from).collect()
+                            } else {
+                                extract_fields(&record, field_pos, config.pad_missing)
+                                    .into_iter()
+                                    .map(String::from)
+                                    .collect()
+                            });
+                        }
+                        for row in transpose(&rows) {
+                            writer.write_row(row)?;
+                        }
+                        continue;
+                    }
+
+                    for record in records {
                         let record = record?;
-                        writer.write_record(extract_fields(&record, field_pos))?;
+                        let fields = extract_fields(&record, field_pos, config.pad_missing);
+                        if config.sum {
+                            add_to_sums(&mut sums, &fields, config.strict_sum)?;
+                        } else {
+                            writer.write_row(fields)?;
+                        }
                     }
                 }
                 Bytes(byte_pos) => {
-                    for line in file.lines() {
-                        println!("{}", extract_bytes(&line?, byte_pos));
+                    for line in read_lines(file, config.zero_terminated) {
+                        out.write_all(
+                            extract_bytes(&line?, byte_pos, config.byte_align).as_bytes(),
+                        )?;
+                        out.write_all(&[terminator])?;
                     }
                 }
                 Chars(char_pos) => {
-                    for line in file.lines() {
-                        println!("{}", extract_chars(&line?, char_pos));
+                    for line in read_lines(file, config.zero_terminated) {
+                        out.write_all(extract_chars(&line?, char_pos).as_bytes())?;
+                        out.write_all(&[terminator])?;
                     }
                 }
             },
         }
     }
+
+    if config.sum {
+        let mut writer = WriterBuilder::new()
+            .delimiter(output_delimiter)
+            .terminator(Terminator::Any(terminator))
+            .from_writer(out);
+        writer.write_record(sums.iter().map(f64::to_string))?;
+    }
+    if invalid > 0 {
+        return Err(format!("{} invalid record(s) found", invalid).into());
+    }
+    Ok(())
+}
+
+// 問題のあるレコードを行番号付きで標準エラーに出し、その数を返す
+fn validate(file: impl BufRead, filename: &str, delimiter: u8, terminator: u8) -> MyResult<usize> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .terminator(Terminator::Any(terminator))
+        .from_reader(file);
+    let mut invalid = 0;
+    for record in reader.records() {
+        let err = match record {
+            Ok(_) => continue,
+            Err(err) => err,
+        };
+        let (line, message) = match err.kind() {
+            ErrorKind::UnequalLengths {
+                pos,
+                expected_len,
+                len,
+            } => (
+                pos.as_ref().map(|pos| pos.line()),
+                format!("expected {} fields, found {}", expected_len, len),
+            ),
+            ErrorKind::Utf8 { pos, err } => (
+                pos.as_ref().map(|pos| pos.line()),
+                format!("invalid UTF-8 in field {}", err.field() + 1),
+            ),
+            // 読み込みエラーは続けても意味がない
+            _ => return Err(format!("{}: {}", filename, err).into()),
+        };
+        match line {
+            Some(line) => eprintln!("{}:{}: {}", filename, line, message),
+            None => eprintln!("{}: {}", filename, message),
+        }
+        invalid += 1;
+    }
+    Ok(invalid)
+}
+
+fn add_to_sums(sums: &mut Vec<f64>, fields: &[&str], strict: bool) -> MyResult<()> {
+    if sums.len() < fields.len() {
+        sums.resize(fields.len(), 0.0);
+    }
+    for (sum, field) in sums.iter_mut().zip(fields) {
+        match field.trim().parse::<f64>() {
+            Ok(value) => *sum += value,
+            Err(_) if strict => return Err(format!("not a number: \"{}\"", field).into()),
+            // 数値でないフィールド (ヘッダなど) は読み飛ばす
+            Err(_) => {}
+        }
+    }
     Ok(())
 }
 
@@ -66,8 +289,8 @@ fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
     let chars: Vec<_> = line.chars().collect();
     let mut result = String::new();
 
-    for range in char_pos.iter().cloned() {
-        for i in range {
+    for range in char_pos.iter() {
+        for i in range.start..range.end.min(chars.len()) {
             if let Some(val) = chars.get(i) {
                 result.push(*val);
             }
@@ -77,32 +300,64 @@ fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
     result
 }
 
-fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
+fn extract_bytes(line: &str, byte_pos: &[Range<usize>], byte_align: bool) -> String {
     let bytes = line.as_bytes();
     let result: Vec<u8> = byte_pos
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| bytes.get(i)))
+        .flat_map(|range| {
+            let mut start = range.start.min(bytes.len());
+            let mut end = range.end.min(bytes.len());
+            if byte_align {
+                // 文字の途中から始まる場合は文字の先頭まで広げ、途中で終わる場合はその文字を含めない
+                while !line.is_char_boundary(start) {
+                    start -= 1;
+                }
+                while !line.is_char_boundary(end) {
+                    end -= 1;
+                }
+            }
+            &bytes[start..end.max(start)]
+        })
         .cloned()
         .collect();
 
     String::from_utf8_lossy(&result).to_string()
 }
 
-// fn extract_fields<'a>(record: &'a StringRecord, field_pos: &[Range<usize>]) -> Vec<&'a str> {
-//     field_pos
-//         .iter()
-//         .cloned()
-//         .flat_map(|range| range.filter_map(|i| record.get(i)))
-//         .collect()
-// }
-
-fn extract_fields(record: &StringRecord, field_pos: &[Range<usize>]) -> Vec<String> {
+// レコードごとに String を作らないよう、StringRecord から借用したまま返す
+fn extract_fields<'a>(
+    record: &'a StringRecord,
+    field_pos: &[Range<usize>],
+    pad_missing: bool,
+) -> Vec<&'a str> {
     field_pos
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| record.get(i)))
-        .map(String::from)
+        .flat_map(|range| {
+            // 終端のない範囲 ("2-") はレコードの末尾までにする。埋めないなら
+            // 閉じた範囲もレコードの長さで切り、巨大な範囲で空回りしないようにする
+            let end = if range.end == usize::MAX || !pad_missing {
+                range.end.min(record.len())
+            } else {
+                range.end
+            };
+            (range.start..end).filter_map(|i| match record.get(i) {
+                // 範囲外のフィールドは空文字で埋める
+                None if pad_missing => Some(""),
+                field => field,
+            })
+        })
+        .collect()
+}
+
+fn transpose(rows: &[Vec<String>]) -> Vec<Vec<&str>> {
+    let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+    (0..width)
+        .map(|i| {
+            rows.iter()
+                // 足りないフィールドは空文字で埋める
+                .map(|row| row.get(i).map_or("", String::as_str))
+                .collect()
+        })
         .collect()
 }
 
@@ -142,6 +397,22 @@ pub fn get_args() -> MyResult<Config> {
                 .default_value("\t")
                 .help("Field delimiter"),
         )
+        .arg(
+            Arg::new("whitespace")
+                .short('w')
+                .long("whitespace")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["delimiter", "bytes", "chars"])
+                .help("Split fields on runs of whitespace"),
+        )
+        .arg(
+            Arg::new("output_delimiter")
+                .value_name("DELIMITER")
+                .short('o')
+                .long("output-delimiter")
+                .conflicts_with_all(["bytes", "chars"])
+                .help("Output field delimiter (defaults to the input delimiter)"),
+        )
         .arg(
             Arg::new("fields")
                 .value_name("FIELDS")
@@ -150,6 +421,84 @@ pub fn get_args() -> MyResult<Config> {
                 .conflicts_with_all(vec!["bytes", "chars"])
                 .help("Selected fields"),
         )
+        .arg(
+            Arg::new("names_file")
+                .value_name("FILE")
+                .long("names-file")
+                .requires("fields")
+                .help("Read column names (one per line) to select fields by name"),
+        )
+        .arg(
+            Arg::new("pad_missing")
+                .long("pad-missing")
+                .action(ArgAction::SetTrue)
+                .requires("fields")
+                .help("Output empty fields for positions beyond the end of a record"),
+        )
+        .arg(
+            Arg::new("byte_align")
+                .long("byte-align")
+                .action(ArgAction::SetTrue)
+                .requires("bytes")
+                .help("Do not split multibyte characters when selecting bytes"),
+        )
+        .arg(
+            Arg::new("only_delimited")
+                .short('s')
+                .long("only-delimited")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["bytes", "chars"])
+                .help("Do not print lines not containing delimiters"),
+        )
+        .arg(
+            Arg::new("transpose")
+                .long("transpose")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["bytes", "chars"])
+                .help("Swap rows and columns of the (selected) fields"),
+        )
+        .arg(
+            Arg::new("sum")
+                .long("sum")
+                .action(ArgAction::SetTrue)
+                .requires("fields")
+                .conflicts_with("transpose")
+                .help("Print the sum of each selected field instead of the fields"),
+        )
+        .arg(
+            Arg::new("strict_sum")
+                .long("strict-sum")
+                .action(ArgAction::SetTrue)
+                .requires("sum")
+                .help("Fail on non-numeric fields instead of skipping them"),
+        )
+        .arg(
+            Arg::new("no_overlap")
+                .long("no-overlap")
+                .action(ArgAction::SetTrue)
+                .help("Fail if the selected positions overlap"),
+        )
+        .arg(
+            Arg::new("collapse_ranges")
+                .long("collapse-ranges")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("no_overlap")
+                .help("Sort and merge overlapping or adjacent positions before selecting"),
+        )
+        .arg(
+            Arg::new("validate")
+                .long("validate")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["whitespace", "bytes", "chars", "transpose", "sum"])
+                .help("Report records with inconsistent field counts or invalid UTF-8"),
+        )
+        .arg(
+            Arg::new("zero_terminated")
+                .short('z')
+                .long("zero-terminated")
+                .action(ArgAction::SetTrue)
+                .help("Line delimiter is NUL, not newline"),
+        )
         .get_matches();
 
     let files = matches
@@ -163,26 +512,81 @@ pub fn get_args() -> MyResult<Config> {
         return Err(format!("--delim \"{}\" must be a single byte", delimiter).into());
     }
 
-    let extract = if let Some(range) = matches.get_one::<String>("bytes") {
+    let output_delimiter = match matches.get_one::<String>("output_delimiter") {
+        Some(delimiter) => match delimiter.as_bytes() {
+            [byte] => Some(*byte),
+            _ => {
+                return Err(
+                    format!("--output-delimiter \"{}\" must be a single byte", delimiter).into(),
+                )
+            }
+        },
+        None => None,
+    };
+
+    let names = match matches.get_one::<String>("names_file") {
+        Some(filename) => {
+            let contents =
+                std::fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+            Some(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(String::from)
+                    .collect::<Vec<_>>(),
+            )
+        }
+        None => None,
+    };
+
+    let mut extract = if let Some(range) = matches.get_one::<String>("bytes") {
         Bytes(parse_pos(range)?)
     } else if let Some(range) = matches.get_one::<String>("chars") {
         Chars(parse_pos(range)?)
     } else if let Some(range) = matches.get_one::<String>("fields") {
-        Fields(parse_pos(range)?)
+        Fields(match &names {
+            Some(names) => parse_names(range, names)?,
+            None => parse_pos(range)?,
+        })
+    } else if matches.get_flag("transpose") || matches.get_flag("validate") {
+        // --transpose だけなら全フィールドを対象にする
+        Fields(vec![])
     } else {
         return Err("the following required arguments were not provided:\n  \
         <--fields <FIELDS>|--bytes <BYTES>|--chars <CHARS>>"
             .into());
     };
+    if matches.get_flag("no_overlap") {
+        match &extract {
+            Fields(pos) | Bytes(pos) | Chars(pos) => check_overlap(pos)?,
+        }
+    }
+    if matches.get_flag("collapse_ranges") {
+        match &mut extract {
+            Fields(pos) | Bytes(pos) | Chars(pos) => *pos = collapse_ranges(pos),
+        }
+    }
 
     Ok(Config {
         files,
         delimiter: *delimiter_bytes.first().unwrap(),
+        whitespace: matches.get_flag("whitespace"),
+        output_delimiter,
         extract,
+        pad_missing: matches.get_flag("pad_missing"),
+        byte_align: matches.get_flag("byte_align"),
+        only_delimited: matches.get_flag("only_delimited"),
+        transpose: matches.get_flag("transpose"),
+        sum: matches.get_flag("sum"),
+        strict_sum: matches.get_flag("strict_sum"),
+        zero_terminated: matches.get_flag("zero_terminated"),
+        names,
+        validate: matches.get_flag("validate"),
     })
 }
 
-fn parse_pos(range: &str) -> MyResult<PositionList> {
+pub fn parse_pos(range: &str) -> MyResult<PositionList> {
     let mut pos = Vec::new();
     let compose_err_msg = |s: &str| format!("illegal list value: {:?}", s);
 
@@ -190,7 +594,7 @@ fn parse_pos(range: &str) -> MyResult<PositionList> {
         match s.parse::<usize>() {
             Err(_) => Err(compose_err_msg(s).into()),
             Ok(v) => {
-                if v <= 0 {
+                if v == 0 {
                     Err(compose_err_msg(s).into())
                 } else {
                     Ok(v - 1)
@@ -199,6 +603,7 @@ fn parse_pos(range: &str) -> MyResult<PositionList> {
         }
     };
 
+    let re = Regex::new(r"^(\d*)-(\d*)$").unwrap();
     for item in range.split(',') {
         if item.contains('+') {
             return Err(compose_err_msg(item).into());
@@ -209,14 +614,24 @@ fn parse_pos(range: &str) -> MyResult<PositionList> {
                 pos.push(item..item + 1);
             }
             Err(_) => {
-                let re = Regex::new(r"(\d+)-(\d+)").unwrap();
                 if !re.is_match(item) {
                     return Err(compose_err_msg(item).into());
                 }
 
+                // "-5" は先頭から、"2-" は末尾までの範囲
                 let cap = re.captures(item).unwrap();
-                let start = convert_to_range_usize(cap.get(1).unwrap().as_str())?;
-                let end = convert_to_range_usize(cap.get(2).unwrap().as_str())?;
+                let (start, end) = match (&cap[1], &cap[2]) {
+                    ("", "") => return Err(compose_err_msg(item).into()),
+                    ("", end) => {
+                        pos.push(0..convert_to_range_usize(end)? + 1);
+                        continue;
+                    }
+                    (start, "") => {
+                        pos.push(convert_to_range_usize(start)?..usize::MAX);
+                        continue;
+                    }
+                    (start, end) => (convert_to_range_usize(start)?, convert_to_range_usize(end)?),
+                };
 
                 if start >= end {
                     return Err(format!(
@@ -235,6 +650,69 @@ fn parse_pos(range: &str) -> MyResult<PositionList> {
     Ok(pos)
 }
 
+// -z なら NUL 区切り、そうでなければ改行区切りで読む
+fn read_lines(
+    file: impl BufRead + 'static,
+    zero_terminated: bool,
+) -> Box<dyn Iterator<Item = MyResult<String>>> {
+    if zero_terminated {
+        Box::new(file.split(b'\0').map(|line| Ok(String::from_utf8(line?)?)))
+    } else {
+        Box::new(file.lines().map(|line| Ok(line?)))
+    }
+}
+
+// 列名で指定されたものはその位置に、それ以外は通常の位置指定として解析する
+fn parse_names(range: &str, names: &[String]) -> MyResult<PositionList> {
+    let mut pos = vec![];
+    for item in range.split(',') {
+        match names.iter().position(|name| name == item) {
+            Some(i) => pos.push(i..i + 1),
+            None => pos.extend(parse_pos(item)?),
+        }
+    }
+    Ok(pos)
+}
+
+// 範囲の重なりはレコードごとではなく、解析した後に一度だけ調べる
+fn check_overlap(pos: &[Range<usize>]) -> MyResult<()> {
+    let mut sorted: Vec<_> = pos.iter().collect();
+    sorted.sort_by_key(|range| range.start);
+    for pair in sorted.windows(2) {
+        if pair[1].start < pair[0].end {
+            return Err(format!(
+                "overlapping ranges: {} and {}",
+                format_range(pair[0]),
+                format_range(pair[1])
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+// 開始位置で並べ、重なるか隣り合う範囲をまとめる。同じ位置は一度しか選ばれなくなる
+pub fn collapse_ranges(pos: &[Range<usize>]) -> PositionList {
+    let mut sorted = pos.to_vec();
+    sorted.sort_by_key(|range| range.start);
+    let mut merged: PositionList = vec![];
+    for range in sorted {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+fn format_range(range: &Range<usize>) -> String {
+    match range.end {
+        usize::MAX => format!("{}-", range.start + 1),
+        end if end == range.start + 1 => end.to_string(),
+        end => format!("{}-{}", range.start + 1, end),
+    }
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
@@ -243,11 +721,15 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
 }
 
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod unit_tests {
     use csv::StringRecord;
 
     use super::parse_pos;
-    use crate::{extract_bytes, extract_chars, extract_fields};
+    use crate::{
+        add_to_sums, check_overlap, collapse_ranges, extract_bytes, extract_chars, extract_fields,
+        parse_names, run_with_writer, transpose, Config, Extract,
+    };
 
     #[test]
     fn test_parse_pos() {
@@ -302,9 +784,6 @@ mod unit_tests {
         let res = parse_pos("1,");
         assert!(res.is_err());
 
-        let res = parse_pos("1-");
-        assert!(res.is_err());
-
         let res = parse_pos("1-1-1");
         assert!(res.is_err());
 
@@ -358,6 +837,100 @@ mod unit_tests {
         let res = parse_pos("15,19-20");
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![14..15, 18..20]);
+
+        // 片側が省略された範囲
+        let res = parse_pos("2-");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![1..usize::MAX]);
+
+        let res = parse_pos("-5");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..5]);
+
+        let res = parse_pos("-1");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..1]);
+
+        let res = parse_pos("3-,1");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![2..usize::MAX, 0..1]);
+
+        let res = parse_pos("-0");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"0\"");
+    }
+
+    #[test]
+    fn test_parse_names() {
+        let names = vec![
+            "title".to_string(),
+            "year".to_string(),
+            "director".to_string(),
+        ];
+        assert_eq!(
+            parse_names("director,title", &names).unwrap(),
+            vec![2..3, 0..1]
+        );
+
+        // 名前と位置は混ぜて使える
+        assert_eq!(
+            parse_names("year,3,1-2", &names).unwrap(),
+            vec![1..2, 2..3, 0..2]
+        );
+
+        let res = parse_names("rating", &names);
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "illegal list value: \"rating\""
+        );
+    }
+
+    #[test]
+    fn test_check_overlap() {
+        let res = check_overlap(&parse_pos("1-3,2").unwrap());
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "overlapping ranges: 1-3 and 2"
+        );
+
+        let res = check_overlap(&parse_pos("1,1").unwrap());
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "overlapping ranges: 1 and 1");
+
+        let res = check_overlap(&parse_pos("5,2-").unwrap());
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "overlapping ranges: 2- and 5");
+
+        // 重ならなければ順番は問わない
+        assert!(check_overlap(&parse_pos("1,2-3,4-").unwrap()).is_ok());
+        assert!(check_overlap(&parse_pos("7,-2,3-5").unwrap()).is_ok());
+        assert!(check_overlap(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_collapse_ranges() {
+        assert_eq!(collapse_ranges(&parse_pos("1-5,3-8").unwrap()), vec![0..8]);
+        assert_eq!(
+            collapse_ranges(&parse_pos("1-5,3-8,2").unwrap()),
+            vec![0..8]
+        );
+        // 隣り合う範囲もまとめ、離れた範囲は順に並べる
+        assert_eq!(
+            collapse_ranges(&parse_pos("7,1-2,3,9-").unwrap()),
+            vec![0..3, 6..7, 8..usize::MAX]
+        );
+        assert_eq!(
+            collapse_ranges(&parse_pos("2-,4").unwrap()),
+            vec![1..usize::MAX]
+        );
+        assert!(collapse_ranges(&[]).is_empty());
+
+        // まとめた後はそれぞれの位置が一度だけ取り出される
+        let pos = collapse_ranges(&parse_pos("1-5,3-8,2").unwrap());
+        assert_eq!(extract_chars("abcdefghij", &pos), "abcdefgh");
+        assert_eq!(extract_bytes("abcdefghij", &pos, false), "abcdefgh");
     }
 
     #[test]
@@ -371,23 +944,128 @@ mod unit_tests {
             extract_chars("あbc", &[0..1, 1..2, 4..5]),
             "あb".to_string()
         );
+        assert_eq!(extract_chars("あbc", &[1..usize::MAX]), "bc".to_string());
+        assert_eq!(
+            extract_chars("あbc", &[2..usize::MAX, 0..1]),
+            "cあ".to_string()
+        );
     }
 
     #[test]
     fn test_extract_bytes() {
-        assert_eq!(extract_bytes("あbc", &[0..1]), "�".to_string());
-        assert_eq!(extract_bytes("あbc", &[0..3]), "あ".to_string());
-        assert_eq!(extract_bytes("あbc", &[0..4]), "あb".to_string());
-        assert_eq!(extract_bytes("あbc", &[0..5]), "あbc".to_string());
-        assert_eq!(extract_bytes("あbc", &[4..5, 3..4]), "cb".to_string());
-        assert_eq!(extract_bytes("あbc", &[0..3, 6..7]), "あ".to_string());
+        assert_eq!(extract_bytes("あbc", &[0..1], false), "�".to_string());
+        assert_eq!(extract_bytes("あbc", &[0..3], false), "あ".to_string());
+        assert_eq!(extract_bytes("あbc", &[0..4], false), "あb".to_string());
+        assert_eq!(extract_bytes("あbc", &[0..5], false), "あbc".to_string());
+        assert_eq!(
+            extract_bytes("あbc", &[4..5, 3..4], false),
+            "cb".to_string()
+        );
+        assert_eq!(
+            extract_bytes("あbc", &[0..3, 6..7], false),
+            "あ".to_string()
+        );
+        assert_eq!(
+            extract_bytes("あbc", &[3..usize::MAX], false),
+            "bc".to_string()
+        );
+
+        // --byte-align なら文字の境界にそろえる
+        assert_eq!(extract_bytes("あbc", &[0..1], true), "".to_string());
+        assert_eq!(extract_bytes("あbc", &[0..3], true), "あ".to_string());
+        assert_eq!(extract_bytes("あbc", &[1..4], true), "あb".to_string());
+        assert_eq!(
+            extract_bytes("あbc", &[2..usize::MAX], true),
+            "あbc".to_string()
+        );
+        assert_eq!(extract_bytes("あbc", &[4..5, 1..2], true), "c".to_string());
+        assert_eq!(extract_bytes("あbc", &[0..2, 3..4], true), "b".to_string());
     }
 
     #[test]
     fn test_extract_fields() {
         let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2]), &["Sham"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 2..3]), &["Captain", "12345"]);
+        assert_eq!(extract_fields(&rec, &[0..1], false), &["Captain"]);
+        assert_eq!(extract_fields(&rec, &[1..2], false), &["Sham"]);
+        assert_eq!(
+            extract_fields(&rec, &[0..1, 2..3], false),
+            &["Captain", "12345"]
+        );
+
+        // 範囲外のフィールドは既定では出力しない
+        let rec = StringRecord::from(vec!["Captain", "Sham"]);
+        assert_eq!(extract_fields(&rec, &[0..3], false), &["Captain", "Sham"]);
+        // --pad-missing なら空文字で埋める
+        assert_eq!(
+            extract_fields(&rec, &[0..3], true),
+            &["Captain", "Sham", ""]
+        );
+        assert_eq!(extract_fields(&rec, &[3..4, 0..1], true), &["", "Captain"]);
+
+        // 終端のない範囲は埋めずにレコードの末尾まで
+        let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
+        assert_eq!(
+            extract_fields(&rec, &[1..usize::MAX], true),
+            &["Sham", "12345"]
+        );
+        assert_eq!(
+            extract_fields(&rec, &[2..usize::MAX, 0..1], false),
+            &["12345", "Captain"]
+        );
+    }
+
+    #[test]
+    fn test_add_to_sums() {
+        let mut sums = vec![];
+        add_to_sums(&mut sums, &["1", "2.5"], false).unwrap();
+        add_to_sums(&mut sums, &["x", " 3 "], false).unwrap();
+        assert_eq!(sums, vec![1.0, 5.5]);
+
+        // 後から列が増えても 0 から足す
+        add_to_sums(&mut sums, &["1", "", "4"], false).unwrap();
+        assert_eq!(sums, vec![2.0, 5.5, 4.0]);
+
+        let res = add_to_sums(&mut sums, &["x"], true);
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "not a number: \"x\"");
+    }
+
+    #[test]
+    fn test_run_with_writer() {
+        let files = vec!["tests/inputs/movies1.csv".to_string()];
+        let mut config = Config::new(files, b',', Extract::Fields(parse_pos("1").unwrap()));
+        let mut out = vec![];
+        run_with_writer(&config, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "title\nThe Blues Brothers\nLes Misérables\n"
+        );
+
+        // オプションはフィールドを直接書き換えて指定する
+        config.extract = Extract::Chars(parse_pos("1-3").unwrap());
+        let mut out = vec![];
+        run_with_writer(&config, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "tit\nThe\nLes\n");
+    }
+
+    #[test]
+    fn test_transpose() {
+        let rows = vec![
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            vec!["1".to_string(), "2".to_string(), "3".to_string()],
+        ];
+        assert_eq!(
+            transpose(&rows),
+            vec![vec!["a", "1"], vec!["b", "2"], vec!["c", "3"]]
+        );
+
+        // 行ごとに長さが違う場合は空文字で埋める
+        let rows = vec![
+            vec!["a".to_string(), "b".to_string()],
+            vec!["1".to_string()],
+        ];
+        assert_eq!(transpose(&rows), vec![vec!["a", "1"], vec!["b", ""]]);
+
+        assert!(transpose(&[]).is_empty());
     }
 }
@@ -339,3 +339,303 @@ fn tsv_c1_8() -> Result<()> {
 fn repeated_value() -> Result<()> {
     run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn pad_missing() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "1,2,3", "--pad-missing"])
+        .write_stdin("a,b\nc,d,e\n")
+        .assert()
+        .success()
+        .stdout("a,b,\nc,d,e\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn huge_field_range() -> Result<()> {
+    // 閉じた範囲でもレコードの長さまでしか見ない
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "1-300000000"])
+        .timeout(std::time::Duration::from_secs(10))
+        .write_stdin("a,b\nc,d\n")
+        .assert()
+        .success()
+        .stdout("a,b\nc,d\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn transpose() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "--transpose"])
+        .write_stdin("a,b,c\n1,2,3\n")
+        .assert()
+        .success()
+        .stdout("a,1\nb,2\nc,3\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn transpose_fields_ragged() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "1,3", "--transpose"])
+        .write_stdin("a,b,c\n1\n")
+        .assert()
+        .success()
+        .stdout("a,1\nc,\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_to_csv() -> Result<()> {
+    run(
+        &[TSV, "-f", "1-3", "-o", ","],
+        "tests/expected/movies1.tsv.f1-3.ocomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn output_delimiter_quotes() -> Result<()> {
+    // 出力区切り文字を含むフィールドはクォートする
+    Command::cargo_bin(PRG)?
+        .args(["-f", "1,2", "--output-delimiter", ","])
+        .write_stdin("a\tb,c\n")
+        .assert()
+        .success()
+        .stdout("a,\"b,c\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_output_delimiter() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([CSV, "-f", "1", "-o", ",,"])
+        .assert()
+        .failure()
+        .stderr("--output-delimiter \",,\" must be a single byte\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sum() -> Result<()> {
+    // ヘッダのような数値でない行は読み飛ばす
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "2", "--sum"])
+        .write_stdin("name,price\napple,100\nbanana,n/a\ncherry,25.5\n")
+        .assert()
+        .success()
+        .stdout("125.5\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sum_multiple_fields() -> Result<()> {
+    run(
+        &[TSV, "-f", "2,2", "--sum"],
+        "tests/expected/movies1.tsv.f2,2.sum.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_strict_sum() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "2", "--sum", "--strict-sum"])
+        .write_stdin("apple,100\nbanana,n/a\n")
+        .assert()
+        .failure()
+        .stderr("not a number: \"n/a\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited() -> Result<()> {
+    let input = "a,b\nno delimiter\nc,d,e\n\nf,\n";
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "1"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("a\nno delimiter\nc\nf\n");
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "1", "-s"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("a\nc\nf\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn whitespace() -> Result<()> {
+    // 連続した空白や先頭の空白はひとつの区切りとして扱う
+    Command::cargo_bin(PRG)?
+        .args(["-w", "-f", "1,3"])
+        .write_stdin("a  b   c\n   d\te  f\ng\n\n")
+        .assert()
+        .success()
+        .stdout("a c\nd f\ng\n\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn whitespace_output_delimiter() -> Result<()> {
+    run(
+        &["tests/inputs/books.txt", "-w", "-f", "2-", "-o", ","],
+        "tests/expected/books.txt.w.f2-.ocomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_whitespace_with_delimiter() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([CSV, "-w", "-d", ",", "-f", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn byte_align() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-b", "2-4"])
+        .write_stdin("あbc\n")
+        .assert()
+        .success()
+        .stdout("��b\n");
+    Command::cargo_bin(PRG)?
+        .args(["-b", "2-4", "--byte-align"])
+        .write_stdin("あbc\n")
+        .assert()
+        .success()
+        .stdout("あb\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> Result<()> {
+    let input = "a,b\nc\0d,e,f\0";
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-d", ",", "-f", "2"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("b\nc\0e\0");
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-c", "1-2"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("a,\0d,\0");
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-w", "-f", "2"])
+        .write_stdin("a b\0c d\0")
+        .assert()
+        .success()
+        .stdout("b\0d\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn collapse_ranges() -> Result<()> {
+    run(
+        &[BOOKS, "-c", "1,1", "--collapse-ranges"],
+        "tests/expected/books.c1.out",
+    )?;
+    run(
+        &[CSV, "-d", ",", "-f", "2-3,1,2", "--collapse-ranges"],
+        "tests/expected/movies1.csv.f1-3.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn no_overlap() -> Result<()> {
+    run(
+        &[CSV, "-d", ",", "-f", "1,2-3", "--no-overlap"],
+        "tests/expected/movies1.csv.f1-3.dcomma.out",
+    )?;
+    Command::cargo_bin(PRG)?
+        .args([CSV, "-d", ",", "-f", "1-3,2", "--no-overlap"])
+        .assert()
+        .failure()
+        .stderr("overlapping ranges: 1-3 and 2\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn names_file() -> Result<()> {
+    // 列数が合わない行があれば警告する
+    Command::cargo_bin(PRG)?
+        .args([
+            "tests/inputs/movies_noheader.csv",
+            "-d",
+            ",",
+            "--names-file",
+            "tests/inputs/movies_names.txt",
+            "-f",
+            "director,year",
+        ])
+        .assert()
+        .success()
+        .stdout("John Landis,1980\nTom Hooper,2012\n1980\n")
+        .stderr("tests/inputs/movies_noheader.csv: record has 2 fields but 3 names were given\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_name() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([
+            "tests/inputs/movies_noheader.csv",
+            "--names-file",
+            "tests/inputs/movies_names.txt",
+            "-f",
+            "rating",
+        ])
+        .assert()
+        .failure()
+        .stderr("illegal list value: \"rating\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn validate() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([CSV, "--validate", "-d", ","])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("");
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/malformed.csv", "--validate", "-d", ","])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(
+            "tests/inputs/malformed.csv:3: expected 3 fields, found 2\n\
+             tests/inputs/malformed.csv:5: expected 3 fields, found 4\n\
+             2 invalid record(s) found\n",
+        );
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use clap::{Arg, ArgAction, Command};
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Debug)]
+pub struct Config {
+    text: Vec<String>,
+    omit_newline: bool,
+    repeat: usize,
+    upper: bool,
+    lower: bool,
+    null: bool,
+    escapes: bool,
+}
+
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<()> {
+    let terminator = if config.null { "\0" } else { "\n" };
+    // -n で省くのは最後の改行だけ
+    let ending = if config.omit_newline { "" } else { terminator };
+    let text = config.text.join(" ");
+    let text = if config.escapes {
+        interpret_escapes(&text)
+    } else {
+        text.into_bytes()
+    };
+    let text = convert_case(text, &config);
+    // -r が大きくてもメモリに溜めずに書き出していく
+    for i in 0..config.repeat {
+        if i > 0 {
+            out.write_all(terminator.as_bytes())?;
+        }
+        out.write_all(&text)?;
+    }
+    out.write_all(ending.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+// \0NNN で UTF-8 でないバイトができた場合は ASCII の範囲だけ変換する
+fn convert_case(text: Vec<u8>, config: &Config) -> Vec<u8> {
+    if !config.upper && !config.lower {
+        return text;
+    }
+    match String::from_utf8(text) {
+        Ok(text) if config.upper => text.to_uppercase().into_bytes(),
+        Ok(text) => text.to_lowercase().into_bytes(),
+        Err(e) => {
+            let mut text = e.into_bytes();
+            if config.upper {
+                text.make_ascii_uppercase();
+            } else {
+                text.make_ascii_lowercase();
+            }
+            text
+        }
+    }
+}
+
+pub fn get_args() -> MyResult<Config> {
+    let matches = Command::new("echor")
+        .version("0.1.0")
+        .author("SeeLog <seelog693@gmail.com>")
+        .about("Rust echo")
+        .arg(
+            Arg::new("text")
+                .value_name("TEXT")
+                .help("Input text (- reads standard input)")
+                .required(true)
+                .num_args(1..),
+        )
+        .arg(
+            Arg::new("omit_newline")
+                .short('n')
+                .action(ArgAction::SetTrue)
+                .help("Do not print newline"),
+        )
+        .arg(
+            Arg::new("repeat")
+                .value_name("N")
+                .short('r')
+                .long("repeat")
+                .num_args(1)
+                .default_value("1")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Print the text N times, one per line"),
+        )
+        .arg(
+            Arg::new("upper")
+                .long("upper")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("lower")
+                .help("Convert the text to uppercase"),
+        )
+        .arg(
+            Arg::new("lower")
+                .long("lower")
+                .action(ArgAction::SetTrue)
+                .help("Convert the text to lowercase"),
+        )
+        .arg(
+            Arg::new("null")
+                .short('z')
+                .long("null")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("omit_newline")
+                .help("End each line with NUL instead of newline"),
+        )
+        .arg(
+            Arg::new("escapes")
+                .short('e')
+                .action(ArgAction::SetTrue)
+                .overrides_with("no_escapes")
+                .help("Enable interpretation of backslash escapes"),
+        )
+        .arg(
+            Arg::new("no_escapes")
+                .short('E')
+                .action(ArgAction::SetTrue)
+                .overrides_with("escapes")
+                .help("Disable interpretation of backslash escapes (default)"),
+        )
+        .get_matches();
+
+    // "-" は標準入力の内容に置き換える。何度あっても読むのは一度だけ
+    let mut stdin_text: Option<String> = None;
+    let mut text = vec![];
+    for v in matches
+        .get_many::<String>("text")
+        .expect("TEXT is required")
+    {
+        text.push(match v.as_str() {
+            "-" => match &stdin_text {
+                Some(t) => t.clone(),
+                None => stdin_text.insert(read_stdin()?).clone(),
+            },
+            _ => v.to_string(),
+        });
+    }
+
+    Ok(Config {
+        text,
+        omit_newline: matches.get_flag("omit_newline"),
+        repeat: *matches.get_one::<u64>("repeat").unwrap() as usize,
+        upper: matches.get_flag("upper"),
+        lower: matches.get_flag("lower"),
+        null: matches.get_flag("null"),
+        escapes: matches.get_flag("escapes"),
+    })
+}
+
+// \n などのエスケープを展開する。知らないエスケープはそのまま残す
+fn interpret_escapes(text: &str) -> Vec<u8> {
+    let mut result = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes().peekable();
+    while let Some(byte) = bytes.next() {
+        if byte != b'\\' {
+            result.push(byte);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'n') => result.push(b'\n'),
+            Some(b't') => result.push(b'\t'),
+            Some(b'r') => result.push(b'\r'),
+            Some(b'\\') => result.push(b'\\'),
+            Some(b'a') => result.push(0x07),
+            Some(b'b') => result.push(0x08),
+            Some(b'f') => result.push(0x0c),
+            Some(b'v') => result.push(0x0b),
+            // \0 の後に最大 3 桁の 8 進数をそのままのバイトとして出す。
+            // 0o377 を超える桁は読まずに普通の文字として残す
+            Some(b'0') => {
+                let mut value = 0u8;
+                for _ in 0..3 {
+                    let next = bytes
+                        .peek()
+                        .filter(|b| (b'0'..=b'7').contains(b))
+                        .and_then(|b| value.checked_mul(8)?.checked_add(b - b'0'));
+                    match next {
+                        Some(next) => {
+                            value = next;
+                            bytes.next();
+                        }
+                        None => break,
+                    }
+                }
+                result.push(value);
+            }
+            Some(byte) => result.extend_from_slice(&[b'\\', byte]),
+            None => result.push(b'\\'),
+        }
+    }
+    result
+}
+
+fn read_stdin() -> MyResult<String> {
+    let text = io::read_to_string(io::stdin()).map_err(|e| format!("stdin: {}", e))?;
+    // 末尾の改行はひとつだけ取り除き、途中の改行はそのまま残す
+    Ok(text
+        .strip_suffix('\n')
+        .map(|t| t.strip_suffix('\r').unwrap_or(t))
+        .unwrap_or(&text)
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, Config};
+
+    fn config(text: &[&str], omit_newline: bool) -> Config {
+        Config {
+            text: text.iter().map(|s| s.to_string()).collect(),
+            omit_newline,
+            repeat: 1,
+            upper: false,
+            lower: false,
+            null: false,
+            escapes: false,
+        }
+    }
+
+    #[test]
+    fn test_run_joins_words() {
+        let mut out = vec![];
+        run(config(&["Hello", "there"], false), &mut out).unwrap();
+        assert_eq!(out, b"Hello there\n");
+
+        // 引数の中の空白はそのまま
+        let mut out = vec![];
+        run(config(&["Hello  there", "!"], false), &mut out).unwrap();
+        assert_eq!(out, b"Hello  there !\n");
+    }
+
+    #[test]
+    fn test_run_omit_newline() {
+        let mut out = vec![];
+        run(config(&["Hello", "there"], true), &mut out).unwrap();
+        assert_eq!(out, b"Hello there");
+
+        // -n で省くのは最後の改行だけ
+        let mut out = vec![];
+        let config = Config {
+            repeat: 2,
+            ..config(&["hi"], true)
+        };
+        run(config, &mut out).unwrap();
+        assert_eq!(out, b"hi\nhi");
+    }
+
+    #[test]
+    fn test_run_streams_repeat() {
+        // 全体を作ってから書くのではなく、書けたところまで出して止まる
+        let mut buf = [0u8; 8];
+        let config = Config {
+            repeat: usize::MAX,
+            ..config(&["hi"], false)
+        };
+        assert!(run(config, &mut &mut buf[..]).is_err());
+        assert_eq!(&buf, b"hi\nhi\nhi");
+    }
+}
@@ -1,30 +1,10 @@
-use clap::{Arg, ArgAction, Command};
+use std::io::{self, BufWriter};
 
 fn main() {
-    let matches = Command::new("echor")
-        .version("0.1.0")
-        .author("SeeLog <seelog693@gmail.com>")
-        .about("Rust echo")
-        .arg(
-            Arg::new("text")
-                .value_name("TEXT")
-                .help("Input text")
-                .required(true)
-                .num_args(1..),
-        )
-        .arg(
-            Arg::new("omit_newline")
-                .short('n')
-                .action(ArgAction::SetTrue)
-                .help("Do not print newline"),
-        )
-        .get_matches();
-    let text = matches
-        .get_many::<String>("text")
-        .expect("TEXT is required")
-        .map(|v| v.to_string())
-        .collect::<Vec<_>>();
-    let omit_newline = matches.get_flag("omit_newline");
-    let ending = if omit_newline { "" } else { "\n" };
-    print!("{}{}", text.join(" "), ending);
+    if let Err(e) = echor::get_args()
+        .and_then(|config| echor::run(config, &mut BufWriter::new(io::stdout().lock())))
+    {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
@@ -34,6 +34,102 @@ fn runs_no_newline() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn repeat() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--repeat", "3", "hello", "there"])
+        .assert()
+        .success()
+        .stdout("hello there\nhello there\nhello there\n");
+    Ok(())
+}
+
+#[test]
+fn repeat_no_newline() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["-n", "-r", "2", "hello"])
+        .assert()
+        .success()
+        .stdout("hello\nhello");
+    Ok(())
+}
+
+#[test]
+fn dies_repeat_zero() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["-r", "0", "hello"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn upper_lower() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--upper", "Hello", "Straße", "ÉCOLE"])
+        .assert()
+        .success()
+        .stdout("HELLO STRASSE ÉCOLE\n");
+    Command::cargo_bin("echor")?
+        .args(["--lower", "Hello", "Straße", "ÉCOLE"])
+        .assert()
+        .success()
+        .stdout("hello straße école\n");
+    Ok(())
+}
+
+#[test]
+fn dies_upper_and_lower() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--upper", "--lower", "hello"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn null() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--null", "a", "b", "c"])
+        .assert()
+        .success()
+        .stdout("a b c\0");
+    Command::cargo_bin("echor")?
+        .args(["-z", "-r", "2", "a"])
+        .assert()
+        .success()
+        .stdout("a\0a\0");
+    Ok(())
+}
+
+#[test]
+fn dies_null_and_no_newline() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["-z", "-n", "a"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn stdin_dash() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["prefix", "-", "suffix"])
+        .write_stdin("from stdin\n")
+        .assert()
+        .success()
+        .stdout("prefix from stdin suffix\n");
+
+    // 途中の改行と、末尾の 2 つ目以降の改行は残す
+    Command::cargo_bin("echor")?
+        .args(["[", "-", "]"])
+        .write_stdin("a\nb\n\n")
+        .assert()
+        .success()
+        .stdout("[ a\nb\n ]\n");
+    Ok(())
+}
+
 fn run(args: &[&str], expected_file: &str) -> TestResult {
     let expected = fs::read_to_string(expected_file)?;
     Command::cargo_bin("echor")?
@@ -63,3 +159,60 @@ fn hello1_no_newline() -> TestResult {
 fn hello2_no_newline() -> TestResult {
     run(&["-n", "Hello", "there"], "tests/expected/hello2.n.txt")
 }
+
+#[test]
+fn escapes() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["-e", r"a\tb\nc\\d"])
+        .assert()
+        .success()
+        .stdout("a\tb\nc\\d\n");
+    Ok(())
+}
+
+#[test]
+fn escapes_octal() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["-e", r"\0101\0\a\b\f\v\r\q"])
+        .assert()
+        .success()
+        .stdout("A\0\x07\x08\x0c\x0b\r\\q\n");
+    Ok(())
+}
+
+#[test]
+fn escapes_octal_bytes() -> TestResult {
+    // UTF-8 にせず、そのままのバイトを出す
+    Command::cargo_bin("echor")?
+        .args(["-e", r"\0377"])
+        .assert()
+        .success()
+        .stdout(&b"\xff\n"[..]);
+    // 0o377 を超える 3 桁目は読まない
+    Command::cargo_bin("echor")?
+        .args(["-e", r"\0400"])
+        .assert()
+        .success()
+        .stdout(" 0\n");
+    Command::cargo_bin("echor")?
+        .args(["-e", "--upper", r"a\0377b"])
+        .assert()
+        .success()
+        .stdout(&b"A\xffB\n"[..]);
+    Ok(())
+}
+
+#[test]
+fn no_escapes_by_default() -> TestResult {
+    Command::cargo_bin("echor")?
+        .arg(r"a\tb")
+        .assert()
+        .success()
+        .stdout("a\\tb\n");
+    Command::cargo_bin("echor")?
+        .args(["-e", "-E", r"a\tb"])
+        .assert()
+        .success()
+        .stdout("a\\tb\n");
+    Ok(())
+}
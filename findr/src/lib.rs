@@ -1,7 +1,16 @@
 use crate::EntryType::*;
 use clap::{builder::PossibleValuesParser, Arg, ArgAction, Command};
 use regex::Regex;
+use sha2::{Digest, Sha256};
 use std::error::Error;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
 use walkdir::{DirEntry, WalkDir};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
@@ -11,58 +20,670 @@ enum EntryType {
     Dir,
     File,
     Link,
+    Executable,
+}
+
+// --printf の書式を分解したもの
+#[derive(Debug, Clone, PartialEq)]
+enum FormatPart {
+    Literal(String),
+    Path,
+    Name,
+    Dir,
+    Size,
+    Depth,
+    Type,
+}
+
+// 現在時刻からの経過時間で絞り込む条件
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TimeFilter {
+    Within(Duration),
+    OlderThan(Duration),
+    Exactly(Duration, Duration),
+}
+
+impl TimeFilter {
+    fn is_match(&self, age: Duration) -> bool {
+        match *self {
+            TimeFilter::Within(d) => age < d,
+            TimeFilter::OlderThan(d) => age > d,
+            TimeFilter::Exactly(d, unit) => age >= d && age < d + unit,
+        }
+    }
+}
+
+// ファイルサイズで絞り込む条件。find と同じく、サイズを単位で切り上げた数で比べる
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SizeFilter {
+    LargerThan(u64, u64),
+    SmallerThan(u64, u64),
+    Exactly(u64, u64),
+}
+
+impl SizeFilter {
+    fn is_match(&self, len: u64) -> bool {
+        match *self {
+            SizeFilter::LargerThan(n, unit) => len.div_ceil(unit) > n,
+            SizeFilter::SmallerThan(n, unit) => len.div_ceil(unit) < n,
+            SizeFilter::Exactly(n, unit) => len.div_ceil(unit) == n,
+        }
+    }
+}
+
+// --same-content-as で比較する基準ファイルのサイズとハッシュ
+#[derive(Debug, Clone, PartialEq)]
+struct ContentRef {
+    size: u64,
+    hash: Vec<u8>,
 }
 
 #[derive(Debug)]
 pub struct Config {
     paths: Vec<String>,
     names: Vec<Regex>,
+    path_names: Vec<Regex>,
     entry_types: Vec<EntryType>,
+    accessed: Option<TimeFilter>,
+    created: Option<TimeFilter>,
+    modified: Option<TimeFilter>,
+    newer: Option<SystemTime>,
+    size: Option<SizeFilter>,
+    printf: Option<Vec<FormatPart>>,
+    same_content: Option<ContentRef>,
+    depth: bool,
+    delete: bool,
+    force: bool,
+    jobs: usize,
+    print0: bool,
+    count_summary: bool,
+    recursively_empty: bool,
+    empty: bool,
+    follow: bool,
+    max_depth: Option<usize>,
+    min_depth: Option<usize>,
+    exec: Option<Exec>,
+    sort: Option<SortKey>,
+}
+
+// --exec のコマンド。batch なら "{} +" の形で、まとめて 1 回だけ実行する
+#[derive(Debug, Clone, PartialEq)]
+struct Exec {
+    args: Vec<String>,
+    batch: bool,
+}
+
+// --sort の並べ方
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortKey {
+    Name,
+    Size,
+    Mtime,
+}
+
+// 見つかったエントリ。並列探索ではサブツリーごとに WalkDir を使うため深さを別に持つ
+#[derive(Debug)]
+struct Found {
+    entry: DirEntry,
+    depth: usize,
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    let type_filter = |entry: &DirEntry| {
-        config.entry_types.is_empty()
-            || config.entry_types.iter().any(|t| match t {
-                Dir => entry.path().is_dir(),
-                File => entry.path().is_file(),
-                Link => entry.path().is_symlink(),
+    let now = SystemTime::now();
+    let mut total = 0;
+    let mut failed = 0;
+    let mut deletes_failed = 0;
+
+    for path in &config.paths {
+        // 空かどうかの集計はツリー全体を通して行うので並列探索は使わない。
+        // -L のループ検出もサブツリーごとの探索では起点を祖先として見られないので同じ
+        let entries = if config.jobs > 1 && !config.recursively_empty && !config.follow {
+            walk_parallel(path, &config, now)
+        } else {
+            walk(new_walker(Path::new(path), 0, &config), 0, &config, now)
+        };
+        // 浅いエントリも空ディレクトリの集計には必要なので、探索した後で除く。
+        // 深さを制限せずに探索した場合は深すぎるエントリもここで除く
+        let max_depth = config.max_depth.filter(|_| walks_whole_tree(&config));
+        let mut entries = match (config.min_depth, max_depth) {
+            (None, None) => entries,
+            _ => entries
+                .into_iter()
+                .filter(|found| config.min_depth.is_none_or(|min| found.depth >= min))
+                .filter(|found| max_depth.is_none_or(|max| found.depth <= max))
+                .collect(),
+        };
+        if let Some(key) = config.sort {
+            sort_entries(&mut entries, key);
+        }
+        total += entries.len();
+        if config.delete {
+            deletes_failed += entries
+                .iter()
+                .filter(|found| !delete_entry(&found.entry, config.force))
+                .count();
+            continue;
+        }
+        if let Some(exec) = &config.exec {
+            failed += run_exec(exec, &entries);
+            continue;
+        }
+        let mut out = io::stdout().lock();
+        match &config.printf {
+            Some(format) => {
+                for found in &entries {
+                    write!(out, "{}", format_entry(found, format))?;
+                }
+            }
+            None => {
+                // -0 なら xargs -0 に渡せるよう NUL で区切る
+                let terminator = if config.print0 { '\0' } else { '\n' };
+                for found in &entries {
+                    write!(out, "{}{}", found.entry.path().display(), terminator)?;
+                }
+            }
+        }
+    }
+    // 標準出力を汚さないよう件数は標準エラーに出す
+    if config.count_summary {
+        eprintln!("{}", total);
+    }
+    if failed > 0 {
+        return Err(format!("--exec: {} command(s) failed", failed).into());
+    }
+    if deletes_failed > 0 {
+        return Err(format!("--delete: {} entries could not be deleted", deletes_failed).into());
+    }
+    Ok(())
+}
+
+// サイズや更新日時が同じものはパスの順に並べる。読めないメタデータは最小として扱う
+fn sort_entries(entries: &mut [Found], key: SortKey) {
+    match key {
+        SortKey::Name => entries.sort_by(|a, b| a.entry.path().cmp(b.entry.path())),
+        SortKey::Size => entries.sort_by_cached_key(|found| {
+            let size = found.entry.metadata().map(|m| m.len()).ok();
+            (size, found.entry.path().to_path_buf())
+        }),
+        SortKey::Mtime => entries.sort_by_cached_key(|found| {
+            let mtime = found.entry.metadata().ok().and_then(|m| m.modified().ok());
+            (mtime, found.entry.path().to_path_buf())
+        }),
+    }
+}
+
+// 失敗したコマンドの数を返す
+fn run_exec(exec: &Exec, entries: &[Found]) -> usize {
+    let paths = entries
+        .iter()
+        .map(|found| found.entry.path().display().to_string())
+        .collect::<Vec<_>>();
+    if exec.batch {
+        if paths.is_empty() {
+            return 0;
+        }
+        // "{}" をすべてのパスに置き換える
+        let args = exec.args.iter().flat_map(|arg| match arg.as_str() {
+            "{}" => paths.clone(),
+            _ => vec![arg.clone()],
+        });
+        usize::from(!run_command(args.collect()))
+    } else {
+        paths
+            .iter()
+            .filter(|path| {
+                let args = exec.args.iter().map(|arg| arg.replace("{}", path));
+                !run_command(args.collect())
             })
+            .count()
+    }
+}
+
+fn run_command(args: Vec<String>) -> bool {
+    match process::Command::new(&args[0]).args(&args[1..]).status() {
+        Ok(status) => status.success(),
+        Err(e) => {
+            eprintln!("{}: {}", args[0], e);
+            false
+        }
+    }
+}
+
+fn is_match(entry: &DirEntry, config: &Config, now: SystemTime) -> bool {
+    let type_match = config.entry_types.is_empty()
+        || config.entry_types.iter().any(|t| match t {
+            Dir => entry.path().is_dir(),
+            File => entry.path().is_file(),
+            Link => entry.path().is_symlink(),
+            Executable => is_executable(entry.path()),
+        });
+    let name_match = config.names.is_empty()
+        || config.names.iter().any(|regex| {
+            regex.is_match(
+                entry
+                    .path()
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_str()
+                    .unwrap_or_default(),
+            )
+        });
+    // --path はファイル名ではなくパス全体に対して調べる
+    let path_match = config.path_names.is_empty() || {
+        let path = entry.path().display().to_string();
+        config.path_names.iter().any(|regex| regex.is_match(&path))
     };
-    let name_filter = |entry: &DirEntry| {
-        config.names.is_empty()
-            || config.names.iter().any(|regex| {
-                regex.is_match(
-                    entry
-                        .path()
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_str()
-                        .unwrap_or_default(),
-                )
-            })
+    type_match
+        && name_match
+        && path_match
+        && time_matches(entry, config.accessed, now, "access", Metadata::accessed)
+        && time_matches(entry, config.created, now, "creation", Metadata::created)
+        && time_matches(
+            entry,
+            config.modified,
+            now,
+            "modification",
+            Metadata::modified,
+        )
+        && newer_matches(entry, config.newer)
+        && size_matches(entry, config.size)
+        && (!config.empty || is_empty(entry))
+        && config
+            .same_content
+            .as_ref()
+            .is_none_or(|reference| same_content(entry, reference))
+}
+
+// 実行ビットのどれかが立っている通常ファイル
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
+
+fn same_content(entry: &DirEntry, reference: &ContentRef) -> bool {
+    if !entry.file_type().is_file() {
+        return false;
+    }
+    // サイズが違えばハッシュを計算するまでもない
+    match entry.metadata() {
+        Ok(metadata) if metadata.len() != reference.size => return false,
+        Err(e) => {
+            eprintln!("{}: {}", entry.path().display(), e);
+            return false;
+        }
+        _ => {}
+    }
+    match hash_file(entry.path()) {
+        Ok(hash) => hash == reference.hash,
+        Err(e) => {
+            eprintln!("{}: {}", entry.path().display(), e);
+            false
+        }
+    }
+}
+
+fn hash_file(path: &Path) -> io::Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+fn read_entries(walker: WalkDir) -> impl Iterator<Item = DirEntry> {
+    walker.into_iter().filter_map(|e| match e {
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+        Ok(entry) => Some(entry),
+    })
+}
+
+// 制限より下にファイルがあるディレクトリを空と見なさないよう、
+// --recursively-empty は --max-depth に関係なくツリー全体をたどる
+fn walks_whole_tree(config: &Config) -> bool {
+    config.recursively_empty
+}
+
+// --max-depth は論理的な深さで数える。たどったリンクも 1 段として数え、
+// サブツリーから探索する場合は起点までの深さを差し引く
+fn new_walker(path: &Path, base_depth: usize, config: &Config) -> WalkDir {
+    // リンクをたどるとループしうるが、walkdir が祖先へのリンクを検出してエラーにする
+    let walker = WalkDir::new(path).follow_links(config.follow);
+    match config.max_depth {
+        Some(max_depth) if !walks_whole_tree(config) => {
+            walker.max_depth(max_depth.saturating_sub(base_depth))
+        }
+        _ => walker,
+    }
+}
+
+fn walk(walker: WalkDir, base_depth: usize, config: &Config, now: SystemTime) -> Vec<Found> {
+    // --delete と --recursively-empty はディレクトリの中身を先に処理する
+    let contents_first = config.depth || config.delete || config.recursively_empty;
+    let mut nonempty = vec![];
+    let mut found = vec![];
+    for result in walker.contents_first(contents_first) {
+        let entry = match result {
+            Err(e) => {
+                eprintln!("{}", e);
+                // 読めなかったものがあるディレクトリは空とは言えない
+                if config.recursively_empty {
+                    track_error(e.depth(), &mut nonempty);
+                }
+                continue;
+            }
+            Ok(entry) => entry,
+        };
+        // 集計のため、他の条件に関係なくすべてのエントリを数える
+        let empty = !config.recursively_empty || track_empty(&entry, &mut nonempty);
+        if empty && is_match(&entry, config, now) {
+            found.push(Found {
+                depth: base_depth + entry.depth(),
+                entry,
+            });
+        }
+    }
+    found
+}
+
+// 中身を先に処理する順番で呼ぶこと。nonempty[d] は深さ d に空でないエントリがあったか
+fn track_empty(entry: &DirEntry, nonempty: &mut Vec<bool>) -> bool {
+    let depth = entry.depth();
+    if nonempty.len() < depth + 2 {
+        nonempty.resize(depth + 2, false);
+    }
+    let empty = entry.file_type().is_dir() && !nonempty[depth + 1];
+    // 子の集計はここで終わりなので、次の兄弟ディレクトリのためにリセットする
+    nonempty[depth + 1] = false;
+    if !empty {
+        nonempty[depth] = true;
+    }
+    empty
+}
+
+// エラーの深さは開けなかったディレクトリ自身のものと、読めなかった子のものがあるので、
+// どちらの場合も空と見なさないよう両方の深さに印を付ける
+fn track_error(depth: usize, nonempty: &mut Vec<bool>) {
+    if nonempty.len() < depth + 2 {
+        nonempty.resize(depth + 2, false);
+    }
+    nonempty[depth] = true;
+    nonempty[depth + 1] = true;
+}
+
+fn walk_parallel(path: &str, config: &Config, now: SystemTime) -> Vec<Found> {
+    // 直下のエントリだけ列挙し、サブディレクトリごとにスレッドへ振り分ける
+    let top_depth = config.max_depth.unwrap_or(1).min(1);
+    let top = read_entries(new_walker(Path::new(path), 0, config).max_depth(top_depth))
+        .collect::<Vec<_>>();
+    let (root, children) = match top.split_first() {
+        Some(split) => split,
+        None => return vec![],
+    };
+    let subdirs = children
+        .iter()
+        .filter(|entry| entry.file_type().is_dir())
+        .collect::<Vec<_>>();
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..subdirs.len()).map(|_| vec![]).collect::<Vec<_>>());
+    thread::scope(|s| {
+        for _ in 0..config.jobs.min(subdirs.len()) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let dir = match subdirs.get(i) {
+                    Some(dir) => dir,
+                    None => break,
+                };
+                let walker = new_walker(dir.path(), 1, config).min_depth(1);
+                let found = walk(walker, 1, config, now);
+                results.lock().unwrap()[i] = found;
+            });
+        }
+    });
+
+    // 逐次探索と同じ順番に並べ直す
+    let contents_first = config.depth || config.delete;
+    let mut subtrees = results.into_inner().unwrap().into_iter();
+    let mut entries = vec![];
+    let push = |entries: &mut Vec<Found>, entry: &DirEntry| {
+        if is_match(entry, config, now) {
+            entries.push(Found {
+                entry: entry.clone(),
+                depth: entry.depth(),
+            });
+        }
     };
+    if !contents_first {
+        push(&mut entries, root);
+    }
+    for child in children {
+        if !child.file_type().is_dir() {
+            push(&mut entries, child);
+            continue;
+        }
+        let subtree = subtrees.next().unwrap_or_default();
+        if contents_first {
+            entries.extend(subtree);
+            push(&mut entries, child);
+        } else {
+            push(&mut entries, child);
+            entries.extend(subtree);
+        }
+    }
+    if contents_first {
+        push(&mut entries, root);
+    }
+    entries
+}
 
-    for path in config.paths {
-        let entries = WalkDir::new(path)
-            .into_iter()
-            .filter_map(|e| match e {
-                Err(e) => {
-                    eprintln!("{}", e);
-                    None
+// 消せなかったら false を返す。中身を先に処理するので、一致した子はディレクトリより先に消える。
+// find -delete と同じく、一致しなかった中身が残っているディレクトリは消さずにエラーにする
+fn delete_entry(entry: &DirEntry, force: bool) -> bool {
+    let path = entry.path();
+    if !force {
+        println!("would delete {}", path.display());
+        return true;
+    }
+    let result = if entry.file_type().is_dir() {
+        fs::remove_dir(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            false
+        }
+    }
+}
+
+fn format_entry(found: &Found, format: &[FormatPart]) -> String {
+    let entry = &found.entry;
+    let mut result = String::new();
+    for part in format {
+        match part {
+            FormatPart::Literal(text) => result.push_str(text),
+            FormatPart::Path => result.push_str(&entry.path().display().to_string()),
+            FormatPart::Name => result.push_str(&entry.file_name().to_string_lossy()),
+            FormatPart::Dir => match entry.path().parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => {
+                    result.push_str(&parent.display().to_string())
                 }
-                Ok(entry) => Some(entry),
-            })
-            .filter(type_filter)
-            .filter(name_filter)
-            .map(|e| e.path().display().to_string())
-            .collect::<Vec<_>>();
-        println!("{}", entries.join("\n"))
+                _ => result.push('.'),
+            },
+            FormatPart::Size => match entry.metadata() {
+                Ok(metadata) => result.push_str(&metadata.len().to_string()),
+                Err(e) => eprintln!("{}: {}", entry.path().display(), e),
+            },
+            FormatPart::Depth => result.push_str(&found.depth.to_string()),
+            FormatPart::Type => {
+                let file_type = entry.file_type();
+                result.push(if file_type.is_symlink() {
+                    'l'
+                } else if file_type.is_dir() {
+                    'd'
+                } else {
+                    'f'
+                });
+            }
+        }
+    }
+    result
+}
+
+fn parse_format(val: &str) -> Result<Vec<FormatPart>, String> {
+    let mut parts = vec![];
+    let mut literal = String::new();
+    let mut chars = val.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => literal.push(match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('0') => '\0',
+                Some('\\') => '\\',
+                Some(c) => return Err(format!("unknown escape '\\{}'", c)),
+                None => return Err("trailing '\\' in format".to_string()),
+            }),
+            '%' => {
+                let part = match chars.next() {
+                    Some('%') => {
+                        literal.push('%');
+                        continue;
+                    }
+                    Some('p') => FormatPart::Path,
+                    Some('f') => FormatPart::Name,
+                    Some('h') => FormatPart::Dir,
+                    Some('s') => FormatPart::Size,
+                    Some('d') => FormatPart::Depth,
+                    Some('y') => FormatPart::Type,
+                    Some(c) => return Err(format!("unknown directive '%{}'", c)),
+                    None => return Err("trailing '%' in format".to_string()),
+                };
+                if !literal.is_empty() {
+                    parts.push(FormatPart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(part);
+            }
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(FormatPart::Literal(literal));
+    }
+    Ok(parts)
+}
+
+// 中身のない通常ファイルか、子のないディレクトリ
+fn is_empty(entry: &DirEntry) -> bool {
+    let result = if entry.file_type().is_dir() {
+        fs::read_dir(entry.path()).map(|mut children| children.next().is_none())
+    } else if entry.file_type().is_file() {
+        entry
+            .metadata()
+            .map(|m| m.len() == 0)
+            .map_err(io::Error::from)
+    } else {
+        Ok(false)
+    };
+    result.unwrap_or_else(|e| {
+        eprintln!("{}: {}", entry.path().display(), e);
+        false
+    })
+}
+
+fn size_matches(entry: &DirEntry, filter: Option<SizeFilter>) -> bool {
+    let filter = match filter {
+        None => return true,
+        Some(filter) => filter,
+    };
+    match entry.metadata() {
+        Err(e) => {
+            eprintln!("{}: cannot read size: {}", entry.path().display(), e);
+            false
+        }
+        Ok(metadata) => filter.is_match(metadata.len()),
+    }
+}
+
+fn time_matches(
+    entry: &DirEntry,
+    filter: Option<TimeFilter>,
+    now: SystemTime,
+    label: &str,
+    get_time: fn(&Metadata) -> io::Result<SystemTime>,
+) -> bool {
+    let filter = match filter {
+        None => return true,
+        Some(filter) => filter,
+    };
+    match entry
+        .metadata()
+        .map_err(io::Error::from)
+        .and_then(|m| get_time(&m))
+    {
+        Err(e) => {
+            eprintln!(
+                "{}: cannot read {} time: {}",
+                entry.path().display(),
+                label,
+                e
+            );
+            false
+        }
+        Ok(time) => filter.is_match(now.duration_since(time).unwrap_or_default()),
+    }
+}
+
+// --newer の基準ファイルより後に更新されたものだけ残す
+fn newer_matches(entry: &DirEntry, newer: Option<SystemTime>) -> bool {
+    let newer = match newer {
+        None => return true,
+        Some(newer) => newer,
+    };
+    match entry
+        .metadata()
+        .map_err(io::Error::from)
+        .and_then(|m| m.modified())
+    {
+        Err(e) => {
+            eprintln!(
+                "{}: cannot read modification time: {}",
+                entry.path().display(),
+                e
+            );
+            false
+        }
+        Ok(time) => time > newer,
     }
-    Ok(())
 }
 
 pub fn get_args() -> MyResult<Config> {
+    // clap の終端は 1 つしか指定できないので、"{} +" の後にも終端の ";" を補う
+    let mut args = vec![];
+    let mut in_exec = false;
+    for arg in std::env::args_os() {
+        let batch_end = in_exec && arg == "+" && args.last().is_some_and(|last| last == "{}");
+        if arg == "--exec" {
+            in_exec = true;
+        } else if arg == ";" || batch_end {
+            in_exec = false;
+        }
+        args.push(arg);
+        if batch_end {
+            args.push(";".into());
+        }
+    }
+
     let matches = Command::new("findr")
         .version("0.1.0")
         .author("SeeLog")
@@ -84,6 +705,15 @@ pub fn get_args() -> MyResult<Config> {
                 .value_parser(|s: &str| Regex::new(s))
                 .help("File name(s)"),
         )
+        .arg(
+            Arg::new("path_names")
+                .value_name("PATTERN")
+                .long("path")
+                .num_args(1..)
+                .action(ArgAction::Append)
+                .value_parser(|s: &str| Regex::new(s))
+                .help("Pattern(s) matched against the whole path"),
+        )
         .arg(
             Arg::new("types")
                 .value_name("TYPE")
@@ -91,9 +721,178 @@ pub fn get_args() -> MyResult<Config> {
                 .long("type")
                 .num_args(0..)
                 .action(ArgAction::Append)
-                .value_parser(PossibleValuesParser::new(&["d", "f", "l"])),
+                .value_parser(PossibleValuesParser::new(["d", "f", "l", "x"]))
+                .help("Entry type (d: directory, f: file, l: link, x: executable file)"),
         )
-        .get_matches();
+        .arg(
+            Arg::new("accessed")
+                .value_name("[+-]TIME")
+                .long("accessed")
+                .num_args(1)
+                .allow_hyphen_values(true)
+                .value_parser(parse_time_filter)
+                .help("Access time (-N: within N, +N: older than N; units s/m/h/d/w, default d)"),
+        )
+        .arg(
+            Arg::new("modified")
+                .value_name("[+-]TIME")
+                .long("mtime")
+                .num_args(1)
+                .allow_hyphen_values(true)
+                .value_parser(parse_time_filter)
+                .help("Modification time (-N: within N, +N: older than N; units s/m/h/d/w, default d)"),
+        )
+        .arg(
+            Arg::new("newer")
+                .value_name("FILE")
+                .long("newer")
+                .num_args(1)
+                .help("Entries modified more recently than FILE"),
+        )
+        .arg(
+            Arg::new("size")
+                .value_name("[+-]SIZE")
+                .long("size")
+                .num_args(1)
+                .allow_hyphen_values(true)
+                .value_parser(parse_size_filter)
+                .help(
+                    "Size rounded up to units (-N: less, +N: more; units c/w/b/k/M/G, default b)",
+                ),
+        )
+        .arg(
+            Arg::new("created")
+                .value_name("[+-]TIME")
+                .long("created")
+                .num_args(1)
+                .allow_hyphen_values(true)
+                .value_parser(parse_time_filter)
+                .help("Creation time (-N: within N, +N: older than N; units s/m/h/d/w, default d)"),
+        )
+        .arg(
+            Arg::new("printf")
+                .value_name("FORMAT")
+                .long("printf")
+                .num_args(1)
+                .value_parser(parse_format)
+                .help("Print entries using FORMAT (%p %f %h %s %d %y %%, \\n \\t \\0 \\\\)"),
+        )
+        .arg(
+            Arg::new("same_content")
+                .value_name("FILE")
+                .long("same-content-as")
+                .num_args(1)
+                .help("Files with the same content as FILE"),
+        )
+        .arg(
+            Arg::new("print0")
+                .short('0')
+                .long("print0")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("printf")
+                .help("Separate results with NUL instead of newline"),
+        )
+        .arg(
+            Arg::new("count_summary")
+                .long("count-summary")
+                .action(ArgAction::SetTrue)
+                .help("Print the number of matched entries to stderr"),
+        )
+        .arg(
+            Arg::new("depth")
+                .long("depth")
+                .action(ArgAction::SetTrue)
+                .help("Process directory contents before the directory itself"),
+        )
+        .arg(
+            Arg::new("recursively_empty")
+                .long("recursively-empty")
+                .action(ArgAction::SetTrue)
+                .help("Directories that contain nothing but empty directories"),
+        )
+        .arg(
+            Arg::new("empty")
+                .long("empty")
+                .action(ArgAction::SetTrue)
+                .help("Empty files and directories without entries"),
+        )
+        .arg(
+            Arg::new("follow")
+                .short('L')
+                .long("follow")
+                .action(ArgAction::SetTrue)
+                .help("Follow symbolic links (links back to an ancestor are reported and skipped)"),
+        )
+        .arg(
+            Arg::new("max_depth")
+                .value_name("N")
+                .long("max-depth")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize))
+                .help("Descend at most N levels; a followed link counts as one level"),
+        )
+        .arg(
+            Arg::new("min_depth")
+                .value_name("N")
+                .long("min-depth")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize))
+                .help("Do not report entries shallower than N levels (0 is the starting path)"),
+        )
+        .arg(
+            Arg::new("sort")
+                .value_name("KEY")
+                .long("sort")
+                .num_args(1)
+                .value_parser(["name", "size", "mtime"])
+                .conflicts_with("delete")
+                .help("Sort the results of each path by name, size or mtime"),
+        )
+        .arg(
+            Arg::new("exec")
+                .value_name("COMMAND")
+                .long("exec")
+                .num_args(1..)
+                .allow_hyphen_values(true)
+                .value_terminator(";")
+                .conflicts_with_all(["delete", "printf", "print0"])
+                .help(
+                    "Run COMMAND for each match, ending with ';' ({} is the path), \
+                       or once for all matches, ending with '{} +'",
+                ),
+        )
+        .arg(
+            Arg::new("delete")
+                .long("delete")
+                .action(ArgAction::SetTrue)
+                .help("Delete matched entries (dry run unless --force is given)"),
+        )
+        .arg(
+            Arg::new("dry_run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .requires("delete")
+                .help("Only print what --delete would remove (default)"),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .requires("delete")
+                .conflicts_with("dry_run")
+                .help("Actually delete entries with --delete"),
+        )
+        .arg(
+            Arg::new("jobs")
+                .value_name("N")
+                .short('j')
+                .long("jobs")
+                .num_args(1)
+                .default_value("1")
+                .value_parser(parse_jobs)
+                .help("Traverse subdirectories with N threads"),
+        )
+        .get_matches_from(args);
 
     let paths = matches
         .get_many::<String>("paths")
@@ -105,6 +904,11 @@ pub fn get_args() -> MyResult<Config> {
         .unwrap_or_default()
         .cloned()
         .collect();
+    let path_names = matches
+        .get_many::<Regex>("path_names")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
 
     let entry_types = matches
         .get_many::<String>("types")
@@ -113,13 +917,162 @@ pub fn get_args() -> MyResult<Config> {
             "d" => Dir,
             "f" => File,
             "l" => Link,
+            "x" => Executable,
             _ => unreachable!(),
         })
-        .collect();
+        .collect::<Vec<_>>();
+    if cfg!(not(unix)) && entry_types.contains(&Executable) {
+        return Err("--type x is only supported on Unix".into());
+    }
+
+    let accessed = matches.get_one::<TimeFilter>("accessed").copied();
+    let created = matches.get_one::<TimeFilter>("created").copied();
+    let modified = matches.get_one::<TimeFilter>("modified").copied();
+    let newer = match matches.get_one::<String>("newer") {
+        None => None,
+        Some(filename) => Some(
+            fs::metadata(filename)
+                .and_then(|m| m.modified())
+                .map_err(|e| format!("{}: {}", filename, e))?,
+        ),
+    };
+    let size = matches.get_one::<SizeFilter>("size").copied();
+    if created.is_some() {
+        // 作成日時を取得できないプラットフォーム/ファイルシステムでは最初にエラーにする
+        if let Err(e) = fs::metadata(".").and_then(|m| m.created()) {
+            return Err(format!("--created is not supported on this platform: {}", e).into());
+        }
+    }
+
+    let exec = matches.get_many::<String>("exec").map(|values| {
+        let mut args = values.cloned().collect::<Vec<_>>();
+        let batch = args.ends_with(&["{}".to_string(), "+".to_string()]);
+        if batch {
+            args.pop();
+        }
+        Exec { args, batch }
+    });
+
+    let same_content = match matches.get_one::<String>("same_content") {
+        None => None,
+        Some(filename) => {
+            let size = fs::metadata(filename)
+                .and_then(|m| {
+                    if m.is_file() {
+                        Ok(m.len())
+                    } else {
+                        Err(io::Error::other("not a regular file"))
+                    }
+                })
+                .map_err(|e| format!("{}: {}", filename, e))?;
+            let hash =
+                hash_file(Path::new(filename)).map_err(|e| format!("{}: {}", filename, e))?;
+            Some(ContentRef { size, hash })
+        }
+    };
 
     Ok(Config {
         paths,
         names,
+        path_names,
         entry_types,
+        accessed,
+        created,
+        modified,
+        newer,
+        size,
+        printf: matches.get_one::<Vec<FormatPart>>("printf").cloned(),
+        same_content,
+        depth: matches.get_flag("depth"),
+        delete: matches.get_flag("delete"),
+        force: matches.get_flag("force"),
+        jobs: *matches.get_one::<usize>("jobs").unwrap(),
+        print0: matches.get_flag("print0"),
+        count_summary: matches.get_flag("count_summary"),
+        recursively_empty: matches.get_flag("recursively_empty"),
+        empty: matches.get_flag("empty"),
+        follow: matches.get_flag("follow"),
+        max_depth: matches.get_one::<usize>("max_depth").copied(),
+        min_depth: matches.get_one::<usize>("min_depth").copied(),
+        exec,
+        sort: matches
+            .get_one::<String>("sort")
+            .map(|key| match key.as_str() {
+                "size" => SortKey::Size,
+                "mtime" => SortKey::Mtime,
+                _ => SortKey::Name,
+            }),
     })
 }
+
+fn parse_jobs(val: &str) -> Result<usize, String> {
+    match val.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("invalid number of jobs '{}'", val)),
+    }
+}
+
+fn parse_duration(val: &str) -> Result<Duration, String> {
+    let err = || format!("invalid duration '{}'", val);
+    let (num, unit) = match val.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => val.split_at(i),
+        None => (val, "d"),
+    };
+    let num = num.parse::<u64>().map_err(|_| err())?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => return Err(err()),
+    };
+    let secs = num.checked_mul(secs).ok_or_else(err)?;
+    Ok(Duration::from_secs(secs))
+}
+
+// 単位なしは find と同じく 512 バイトのブロック数
+fn parse_size_filter(val: &str) -> Result<SizeFilter, String> {
+    let err = || format!("invalid size '{}'", val);
+    let rest = val.trim_start_matches(['+', '-']);
+    let (num, unit) = match rest.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => rest.split_at(i),
+        None => (rest, "b"),
+    };
+    let num = num.parse::<u64>().map_err(|_| err())?;
+    let unit = match unit {
+        "c" => 1,
+        "w" => 2,
+        "b" => 512,
+        "k" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(err()),
+    };
+    match val.len() - rest.len() {
+        0 => Ok(SizeFilter::Exactly(num, unit)),
+        1 if val.starts_with('+') => Ok(SizeFilter::LargerThan(num, unit)),
+        1 => Ok(SizeFilter::SmallerThan(num, unit)),
+        _ => Err(err()),
+    }
+}
+
+fn parse_time_filter(val: &str) -> Result<TimeFilter, String> {
+    if let Some(rest) = val.strip_prefix('-') {
+        Ok(TimeFilter::Within(parse_duration(rest)?))
+    } else if let Some(rest) = val.strip_prefix('+') {
+        Ok(TimeFilter::OlderThan(parse_duration(rest)?))
+    } else {
+        // 符号なしは「N 単位前」の幅 1 単位の範囲 (find の -atime N と同じ)
+        let duration = parse_duration(val)?;
+        let unit = match val.trim_start_matches(|c: char| c.is_ascii_digit()) {
+            "" => parse_duration("1")?,
+            unit => parse_duration(&format!("1{}", unit))?,
+        };
+        // 範囲の終わりも表せることを確かめておく
+        if duration.checked_add(unit).is_none() {
+            return Err(format!("invalid duration '{}'", val));
+        }
+        Ok(TimeFilter::Exactly(duration, unit))
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use assert_cmd::Command;
+use filetime::FileTime;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
 use std::{borrow::Cow, fs, path::Path};
@@ -48,9 +49,9 @@ fn dies_bad_name() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn dies_bad_type() -> Result<()> {
-    let expected = "error: invalid value 'x' for '--type [<TYPE>...]'";
+    let expected = "error: invalid value 'z' for '--type [<TYPE>...]'";
     Command::cargo_bin(PRG)?
-        .args(["--type", "x"])
+        .args(["--type", "z"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -59,14 +60,14 @@ fn dies_bad_type() -> Result<()> {
 
 // --------------------------------------------------
 #[cfg(windows)]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Owned(format!("{}.windows", expected_file))
     format!("{}.windows", expected_file).into()
 }
 
 // --------------------------------------------------
 #[cfg(not(windows))]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Borrowed(expected_file)
     expected_file.into()
 }
@@ -239,6 +240,25 @@ fn name_csv_mp3() -> Result<()> {
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn path_pattern() -> Result<()> {
+    // 区切り文字が OS で異なるので . で受ける
+    run(
+        &["tests/inputs", "--path", "a.b"],
+        "tests/expected/path_a_b_pattern.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn path_pattern_name_csv() -> Result<()> {
+    run(
+        &["tests/inputs", "--path", "a.b", "-n", ".*[.]csv"],
+        "tests/expected/path_a_b_name_csv.txt",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn name_txt_path_a_d() -> Result<()> {
@@ -308,3 +328,829 @@ fn unreadable_dir() -> Result<()> {
     assert!(stderr.contains("cant-touch-this: Permission denied"));
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn created_within() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let file = dir.path().join("new.txt");
+    fs::write(&file, "new")?;
+    if fs::metadata(&file)?.created().is_err() {
+        // 作成日時を取得できない環境ではスキップ
+        return Ok(());
+    }
+    let dirname = dir.path().to_str().unwrap();
+
+    let out = Command::cargo_bin(PRG)?
+        .args([dirname, "-t", "f", "--created", "-1h"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(out.get_output().stdout.clone())?;
+    assert_eq!(stdout.trim_end(), file.to_str().unwrap());
+
+    let out = Command::cargo_bin(PRG)?
+        .args([dirname, "-t", "f", "--created", "+1h"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(out.get_output().stdout.clone())?;
+    assert_eq!(stdout.trim_end(), "");
+    Ok(())
+}
+
+// --------------------------------------------------
+fn make_mtime_tree() -> Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    let now = FileTime::now();
+    for (name, days) in [("today.txt", 0), ("old.txt", 3), ("ancient.txt", 10)] {
+        let file = dir.path().join(name);
+        fs::write(&file, name)?;
+        let time = FileTime::from_unix_time(now.unix_seconds() - days * 24 * 60 * 60, 0);
+        filetime::set_file_mtime(&file, time)?;
+    }
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+fn mtime() -> Result<()> {
+    let dir = make_mtime_tree()?;
+    let dirname = dir.path().to_str().unwrap();
+    let path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+
+    assert_eq!(
+        sorted_output(&[dirname, "-t", "f", "--mtime", "-1"])?,
+        [path("today.txt")]
+    );
+    assert_eq!(
+        sorted_output(&[dirname, "-t", "f", "--mtime", "+7"])?,
+        [path("ancient.txt")]
+    );
+    assert_eq!(
+        sorted_output(&[dirname, "-t", "f", "--mtime", "3"])?,
+        [path("old.txt")]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn newer() -> Result<()> {
+    let dir = make_mtime_tree()?;
+    let dirname = dir.path().to_str().unwrap();
+    let path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+    let reference = path("old.txt");
+
+    assert_eq!(
+        sorted_output(&[dirname, "-t", "f", "--newer", &reference])?,
+        [path("today.txt")]
+    );
+
+    Command::cargo_bin(PRG)?
+        .args([dirname, "--newer", "tests/inputs/no-such-file"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("tests/inputs/no-such-file: "));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn accessed_older_than_now() -> Result<()> {
+    run(
+        &["tests/inputs/a", "-t", "f", "--accessed", "+0s"],
+        "tests/expected/type_f_path_a.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn accessed_within() -> Result<()> {
+    run(
+        &["tests/inputs/a", "-t", "f", "--accessed", "-36500d"],
+        "tests/expected/type_f_path_a.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_accessed() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--accessed", "-1x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid duration '1x'"));
+    // 秒に直すとあふれる値
+    for val in [
+        "99999999999999999w",
+        "+99999999999999999w",
+        "30500568904943w",
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(["--accessed", val])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid duration"));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn printf_name_size() -> Result<()> {
+    run(
+        &["tests/inputs/a", "-t", "f", "--printf", r"%f %s\n"],
+        "tests/expected/printf_f_s.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn printf_no_trailing_newline() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/g.csv", "--printf", "[%y %d %h %p]"])
+        .assert()
+        .success()
+        .stdout("[f 0 tests/inputs tests/inputs/g.csv]");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_printf() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--printf", "%z"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown directive '%z'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+fn make_delete_tree() -> Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("sub"))?;
+    fs::write(dir.path().join("a.txt"), "a")?;
+    fs::write(dir.path().join("b.log"), "b")?;
+    fs::write(dir.path().join("sub").join("c.txt"), "c")?;
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+fn delete_dry_run() -> Result<()> {
+    let dir = make_delete_tree()?;
+    let dirname = dir.path().to_str().unwrap();
+
+    for args in [vec!["--delete"], vec!["--delete", "--dry-run"]] {
+        let cmd = Command::cargo_bin(PRG)?
+            .arg(dirname)
+            .args(["-n", r".*\.txt"])
+            .args(args)
+            .assert()
+            .success();
+        let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+        let mut lines: Vec<&str> = stdout.lines().collect();
+        lines.sort();
+        assert_eq!(
+            lines,
+            vec![
+                format!("would delete {}", dir.path().join("a.txt").display()),
+                format!(
+                    "would delete {}",
+                    dir.path().join("sub").join("c.txt").display()
+                ),
+            ]
+        );
+    }
+
+    assert!(dir.path().join("a.txt").exists());
+    assert!(dir.path().join("b.log").exists());
+    assert!(dir.path().join("sub").join("c.txt").exists());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn delete_force() -> Result<()> {
+    let dir = make_delete_tree()?;
+    Command::cargo_bin(PRG)?
+        .arg(dir.path().to_str().unwrap())
+        .args(["-n", r".*\.txt", "--delete", "--force"])
+        .assert()
+        .success()
+        .stdout("");
+
+    assert!(!dir.path().join("a.txt").exists());
+    assert!(dir.path().join("b.log").exists());
+    assert!(!dir.path().join("sub").join("c.txt").exists());
+    assert!(dir.path().join("sub").exists());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn delete_max_depth() -> Result<()> {
+    let dir = make_delete_tree()?;
+    Command::cargo_bin(PRG)?
+        .arg(dir.path().to_str().unwrap())
+        .args(["-n", r".*\.txt", "--max-depth", "1", "--delete", "--force"])
+        .assert()
+        .success();
+
+    assert!(!dir.path().join("a.txt").exists());
+    assert!(dir.path().join("sub").join("c.txt").exists());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn delete_keeps_nonempty_dir() -> Result<()> {
+    let dir = make_delete_tree()?;
+    let sub = dir.path().join("sub");
+    // sub は一致するが、一致しない c.txt が中にあるので消さない
+    Command::cargo_bin(PRG)?
+        .arg(dir.path().to_str().unwrap())
+        .args(["-t", "d", "-n", "sub", "--delete", "--force"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(sub.to_str().unwrap()));
+
+    assert!(sub.join("c.txt").exists());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_force_without_delete() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .arg("--force")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--delete"));
+    Ok(())
+}
+
+// --------------------------------------------------
+fn make_large_tree() -> Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    for i in 0..16 {
+        for j in 0..8 {
+            let sub = dir.path().join(format!("d{}", i)).join(format!("e{}", j));
+            fs::create_dir_all(&sub)?;
+            for k in 0..8 {
+                fs::write(sub.join(format!("f{}.txt", k)), "")?;
+            }
+            fs::write(sub.join("skip.log"), "")?;
+        }
+    }
+    fs::write(dir.path().join("top.txt"), "")?;
+    Ok(dir)
+}
+
+fn sorted_output(args: &[&str]) -> Result<Vec<String>> {
+    let cmd = Command::cargo_bin(PRG)?.args(args).assert().success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut lines: Vec<String> = stdout.lines().map(|s| s.to_string()).collect();
+    lines.sort();
+    Ok(lines)
+}
+
+// --------------------------------------------------
+#[test]
+fn jobs_matches_sequential() -> Result<()> {
+    let dir = make_large_tree()?;
+    let dirname = dir.path().to_str().unwrap();
+
+    for args in [
+        vec![dirname],
+        vec![dirname, "-t", "f", "-n", r".*\.txt"],
+        vec![dirname, "--depth", "--printf", r"%d %y %p\n"],
+    ] {
+        let sequential = sorted_output(&args)?;
+        assert!(sequential.len() > 1000);
+        for jobs in ["2", "4"] {
+            let mut parallel_args = args.clone();
+            parallel_args.extend(["--jobs", jobs]);
+            assert_eq!(sorted_output(&parallel_args)?, sequential);
+        }
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_jobs() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--jobs", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid number of jobs '0'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn same_content_as() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let reference = dir.path().join("ref.bin");
+    fs::write(&reference, b"\x00\x01duplicate")?;
+    fs::create_dir(dir.path().join("sub"))?;
+    let duplicate = dir.path().join("sub").join("copy.bin");
+    fs::write(&duplicate, b"\x00\x01duplicate")?;
+    // 同じサイズで中身が違うもの、サイズが違うもの
+    fs::write(dir.path().join("same_size.bin"), b"\x00\x01duplicatf")?;
+    fs::write(dir.path().join("other.bin"), b"other")?;
+
+    // 基準ファイル自身もマッチする
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["--same-content-as", reference.to_str().unwrap()])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut lines: Vec<&str> = stdout.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![reference.to_str().unwrap(), duplicate.to_str().unwrap()]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_same_content_as_missing() -> Result<()> {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--same-content-as", &bad])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!("{}: ", bad)));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn print0_count_summary() -> Result<()> {
+    let dir = make_delete_tree()?;
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-t", "f", "--print0", "--count-summary"])
+        .assert()
+        .success();
+    let output = cmd.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone())?;
+    assert!(stdout.ends_with('\0'));
+    assert!(!stdout.contains('\n'));
+    let mut paths: Vec<&str> = stdout.trim_end_matches('\0').split('\0').collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            dir.path().join("a.txt").to_str().unwrap(),
+            dir.path().join("b.log").to_str().unwrap(),
+            dir.path().join("sub").join("c.txt").to_str().unwrap(),
+        ]
+    );
+    assert_eq!(String::from_utf8(output.stderr.clone())?, "3\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn print0_separators() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a/b", "-0", "-t", "f"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    // 区切りは NUL だけで、最後のパスの後にも付く
+    assert!(!stdout.contains('\n'));
+    let mut paths: Vec<&str> = stdout.split_terminator('\0').collect();
+    paths.sort();
+    let dir = Path::new("tests/inputs/a/b");
+    assert_eq!(
+        paths,
+        [
+            dir.join("b.csv").to_str().unwrap(),
+            dir.join("c").join("c.mp3").to_str().unwrap(),
+        ]
+    );
+
+    // 何も見つからなければ何も出さない
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-n", "nothing-matches"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursively_empty() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let empty = dir.path().join("empty");
+    fs::create_dir_all(empty.join("a").join("b"))?;
+    fs::create_dir(empty.join("c"))?;
+    let mixed = dir.path().join("mixed");
+    fs::create_dir_all(mixed.join("hollow"))?;
+    fs::write(mixed.join("file.txt"), "")?;
+
+    for jobs in ["1", "4"] {
+        let cmd = Command::cargo_bin(PRG)?
+            .arg(dir.path())
+            .args(["--recursively-empty", "-j", jobs])
+            .assert()
+            .success();
+        let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+        let mut lines: Vec<&str> = stdout.lines().collect();
+        lines.sort();
+        // 空のディレクトリしか含まない枝は根も含めて報告され、ファイルを含むものは報告されない
+        let expected = [
+            empty.clone(),
+            empty.join("a"),
+            empty.join("a").join("b"),
+            empty.join("c"),
+            mixed.join("hollow"),
+        ];
+        assert_eq!(
+            lines,
+            expected
+                .iter()
+                .map(|p| p.to_str().unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn recursively_empty_walk_error() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let looped = dir.path().join("looped");
+    fs::create_dir(&looped)?;
+    // -L でたどるとループになるので、中身を読めなかったディレクトリになる
+    std::os::unix::fs::symlink(&looped, looped.join("self"))?;
+    let empty = dir.path().join("empty");
+    fs::create_dir(&empty)?;
+
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-L", "--recursively-empty"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout, format!("{}\n", empty.display()));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursively_empty_delete_max_depth() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let hollow = dir.path().join("hollow").join("sub");
+    fs::create_dir_all(&hollow)?;
+    // 深さの制限ちょうどにあるディレクトリの中にファイルがある
+    let mixed = dir.path().join("mixed").join("sub");
+    fs::create_dir_all(&mixed)?;
+    fs::write(mixed.join("keep.txt"), "keep")?;
+
+    Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args([
+            "--recursively-empty",
+            "--max-depth",
+            "2",
+            "--delete",
+            "--force",
+        ])
+        .assert()
+        .success();
+
+    assert!(!dir.path().join("hollow").exists());
+    assert!(mixed.join("keep.txt").exists());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn follow_max_depth() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let real = dir.path().join("real");
+    fs::create_dir_all(real.join("a").join("b"))?;
+    fs::write(real.join("a").join("b").join("deep.txt"), "")?;
+    let link = dir.path().join("link");
+    std::os::unix::fs::symlink(&real, &link)?;
+
+    let dirname = dir.path().to_str().unwrap();
+    let run = |args: &[&str]| -> Result<Vec<String>> {
+        let lines = sorted_output(&[&[dirname], args].concat())?;
+        Ok(lines
+            .iter()
+            .map(|l| l.strip_prefix(dirname).unwrap().to_string())
+            .collect())
+    };
+
+    // リンクの先も実際のディレクトリと同じ深さで数える
+    let expected = ["", "/link", "/link/a", "/real", "/real/a"];
+    assert_eq!(run(&["-L", "--max-depth", "2"])?, expected);
+    assert_eq!(run(&["-L", "--max-depth", "2", "-j", "2"])?, expected);
+
+    // リンクをたどらなければリンク自体だけが見つかる
+    assert_eq!(
+        run(&["--max-depth", "2"])?,
+        ["", "/link", "/real", "/real/a"]
+    );
+    assert_eq!(run(&["-L", "--max-depth", "0", "-j", "2"])?, [""]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn follow_loop_to_root_jobs() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let a = dir.path().join("a");
+    fs::create_dir(&a)?;
+    fs::write(a.join("f"), "")?;
+    // 起点に戻るリンク
+    std::os::unix::fs::symlink(dir.path(), a.join("up"))?;
+    let dirname = dir.path().to_str().unwrap();
+
+    let sequential = sorted_output(&[dirname, "-L"])?;
+    assert_eq!(sequential.len(), 3);
+    assert_eq!(sorted_output(&[dirname, "-L", "-j", "2"])?, sequential);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn type_x() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir()?;
+    let script = dir.path().join("run.sh");
+    fs::write(&script, "#!/bin/sh\n")?;
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+    let data = dir.path().join("data.txt");
+    fs::write(&data, "")?;
+    fs::set_permissions(&data, fs::Permissions::from_mode(0o644))?;
+    // 実行ビット付きのディレクトリは対象外
+    fs::create_dir(dir.path().join("bin"))?;
+
+    Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["--type", "x"])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", script.display()));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_depth() -> Result<()> {
+    run(
+        &["tests/inputs", "--max-depth", "1"],
+        "tests/expected/max_depth_1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn min_max_depth() -> Result<()> {
+    run(
+        &["tests/inputs", "--min-depth", "2", "--max-depth", "2"],
+        "tests/expected/depth_2.txt",
+    )?;
+    run(
+        &[
+            "tests/inputs",
+            "--min-depth",
+            "2",
+            "--max-depth",
+            "2",
+            "-j",
+            "3",
+        ],
+        "tests/expected/depth_2.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn size() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    for (name, len) in [
+        ("empty", 0),
+        ("small", 100),
+        ("medium", 2000),
+        ("large", 5000),
+    ] {
+        fs::write(dir.path().join(name), vec![b'x'; len])?;
+    }
+    let dirname = dir.path().to_str().unwrap();
+    let run = |size: &str| -> Result<Vec<String>> {
+        let lines = sorted_output(&[dirname, "--type", "f", "--size", size])?;
+        Ok(lines
+            .iter()
+            .map(|l| l.rsplit(['/', '\\']).next().unwrap().to_string())
+            .collect())
+    };
+
+    // 単位で切り上げて比べるので 100 バイトは 1k と同じ扱い
+    assert_eq!(run("+1k")?, ["large", "medium"]);
+    assert_eq!(run("-2k")?, ["empty", "small"]);
+    assert_eq!(run("1k")?, ["small"]);
+    assert_eq!(run("100c")?, ["small"]);
+    assert_eq!(run("+4")?, ["large"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_size() -> Result<()> {
+    for bad in ["10x", "+", "--1k", "k"] {
+        Command::cargo_bin(PRG)?
+            .args(["--size", bad])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!("invalid size '{}'", bad)));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn exec() -> Result<()> {
+    let dir = "tests/inputs/a/b";
+    let run = |args: &[&str]| sorted_output(&[&[dir, "-t", "f", "--exec"], args].concat());
+
+    // 1 件ごとに実行し、引数の中の {} も置き換える
+    assert_eq!(
+        run(&["echo", "found:{}", ";"])?,
+        [
+            "found:tests/inputs/a/b/b.csv",
+            "found:tests/inputs/a/b/c/c.mp3"
+        ]
+    );
+    // "{} +" ならまとめて 1 回だけ実行する
+    let batch = run(&["echo", "-n", "{}", "+"])?;
+    assert_eq!(batch.len(), 1);
+    let mut paths: Vec<&str> = batch[0].split(' ').collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        ["tests/inputs/a/b/b.csv", "tests/inputs/a/b/c/c.mp3"]
+    );
+
+    // ";" の後にもオプションを続けられる
+    Command::cargo_bin(PRG)?
+        .args([
+            dir,
+            "--exec",
+            "echo",
+            "{}",
+            ";",
+            "-t",
+            "d",
+            "--max-depth",
+            "0",
+        ])
+        .assert()
+        .success()
+        .stdout("tests/inputs/a/b\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn exec_failure() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a/b", "-t", "f", "--exec", "false", ";"])
+        .assert()
+        .failure()
+        .stderr("--exec: 2 command(s) failed\n");
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a/b", "-t", "f", "--exec", "false", "{}", "+"])
+        .assert()
+        .failure()
+        .stderr("--exec: 1 command(s) failed\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_name() -> Result<()> {
+    let expected = [
+        "a/a.txt",
+        "a/b/b.csv",
+        "a/b/c/c.mp3",
+        "d/b.csv",
+        "d/d.tsv",
+        "d/d.txt",
+        "d/e/e.mp3",
+        "f/f.txt",
+        "g.csv",
+    ]
+    .iter()
+    .map(|name| format!("{}\n", Path::new("tests/inputs").join(name).display()))
+    .collect::<String>();
+    // 並列探索でも同じ順番になる
+    for jobs in ["1", "3"] {
+        Command::cargo_bin(PRG)?
+            .args(["tests/inputs", "-t", "f", "--sort", "name", "-j", jobs])
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_size() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    for (name, len) in [("a", 30), ("b", 10), ("c", 20), ("d", 10)] {
+        fs::write(dir.path().join(name), vec![b'x'; len])?;
+    }
+    let expected = ["b", "d", "c", "a"]
+        .iter()
+        .map(|name| format!("{}\n", dir.path().join(name).display()))
+        .collect::<String>();
+    Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-t", "f", "--sort", "size"])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn empty() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("empty.txt"), "")?;
+    fs::write(dir.path().join("full.txt"), "x")?;
+    fs::create_dir(dir.path().join("empty_dir"))?;
+    fs::create_dir(dir.path().join("full_dir"))?;
+    fs::write(dir.path().join("full_dir").join("nested.txt"), "")?;
+
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["--empty", "--sort", "name"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(
+        stdout,
+        format!(
+            "{}\n{}\n{}\n",
+            dir.path().join("empty.txt").display(),
+            dir.path().join("empty_dir").display(),
+            dir.path().join("full_dir").join("nested.txt").display(),
+        )
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn follow_loop() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let sub = dir.path().join("sub");
+    fs::create_dir(&sub)?;
+    fs::write(sub.join("file.txt"), "")?;
+    std::os::unix::fs::symlink(dir.path(), sub.join("loop"))?;
+
+    // ループは報告するだけで、残りの探索は続ける
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-L", "-t", "f"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("File system loop found"));
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout, format!("{}\n", sub.join("file.txt").display()));
+
+    // たどらなければリンク自体が見つかるだけ
+    Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-t", "l"])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", sub.join("loop").display()))
+        .stderr("");
+    Ok(())
+}
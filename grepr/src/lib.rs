@@ -1,65 +1,504 @@
-use clap::{Arg, ArgAction, Command};
+use clap::{parser::ValueSource, Arg, ArgAction, ArgGroup, Command};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use regex::{Regex, RegexBuilder};
+use regex_automata::{meta, util::syntax, Anchored, Input, MatchKind};
 use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
     error::Error,
     fs::File,
-    io::{self, BufRead, BufReader},
+    io::{self, BufRead, BufReader, IsTerminal, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
 };
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+// 出力する行。context は -A/-B/-C で前後に付け加えた行
+#[derive(Debug, PartialEq)]
+struct Line {
+    number: usize,
+    text: String,
+    context: bool,
+}
+
+// --binary-files の扱い
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryFiles {
+    Binary,
+    Text,
+    WithoutMatch,
+}
+
+const COLOR_MATCH: &str = "\x1b[1;31m";
+const COLOR_DIM: &str = "\x1b[2m";
+const COLOR_RESET: &str = "\x1b[0m";
+
 #[derive(Debug)]
 pub struct Config {
     pattern: Regex,
     files: Vec<String>,
     recursive: bool,
     count: bool,
+    line_number: bool,
     invert_match: bool,
+    strip_prefix: Option<String>,
+    color: bool,
+    passthru: bool,
+    json: bool,
+    byte_range: Option<Range<u64>>,
+    files_with_matches: bool,
+    files_without_match: bool,
+    text_only: bool,
+    max_filesize: Option<u64>,
+    before_context: usize,
+    after_context: usize,
+    dim_context: bool,
+    max_count: Option<usize>,
+    until_match: bool,
+    exclusive: bool,
+    replace_preview: Option<String>,
+    with_filename: Option<bool>,
+    include: GlobSet,
+    exclude: GlobSet,
+    jobs: usize,
+    total: bool,
+    // -o のとき、マッチの最長の終わりを探すための正規表現
+    only_matching: Option<meta::Regex>,
+    overlapping: bool,
+    count_distinct: bool,
+    binary_files: BinaryFiles,
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    let entries = find_files(&config.files, config.recursive);
-    for entry in &entries {
-        match entry {
-            Err(e) => eprintln!("{}", e),
-            Ok(filename) => match open(&filename) {
-                Err(e) => eprintln!("{}: {}", filename, e),
-                Ok(file) => {
-                    let matches = find_lines(file, &config.pattern, config.invert_match);
-                    if entries.len() > 1 {
-                        print_match(&config, matches?, filename, true);
-                    } else {
-                        print_match(&config, matches?, filename, false);
-                    }
+    let entries = find_files(
+        &config.files,
+        config.recursive,
+        config.max_filesize,
+        &config.include,
+        &config.exclude,
+    );
+    // grep と同じく -r か複数ファイルならファイル名を付ける
+    let show_filename = config
+        .with_filename
+        .unwrap_or(config.recursive || entries.len() > 1);
+    let mut distinct = HashSet::new();
+    let total = if config.jobs > 1 {
+        search_parallel(&config, &entries, show_filename, &mut distinct)?
+    } else {
+        let mut total = 0;
+        let mut out = io::stdout().lock();
+        for entry in &entries {
+            match entry {
+                Err(e) => eprintln!("{}", e),
+                Ok(filename) => {
+                    total += search_file(&config, filename, show_filename, &mut out, &mut distinct)?
                 }
-            },
+            }
         }
+        total
+    };
+    if config.total {
+        println!("total:{}", total);
+    }
+    if config.count_distinct {
+        println!("{}", distinct.len());
     }
 
     Ok(())
 }
 
-fn print_match(config: &Config, matches: Vec<String>, filename: &str, show_filename: bool) {
-    if config.count {
-        if show_filename {
-            print!("{}:", filename);
+// ファイルごとの出力はバッファに溜め、見つかった順ではなくファイルの順に書き出す
+fn search_parallel(
+    config: &Config,
+    entries: &[MyResult<String>],
+    show_filename: bool,
+    distinct: &mut HashSet<String>,
+) -> MyResult<usize> {
+    // エラーはスレッド間で共有できないので、ワーカーにはファイル名だけ渡す
+    let filenames: Vec<Option<&str>> = entries
+        .iter()
+        .map(|entry| entry.as_ref().ok().map(String::as_str))
+        .collect();
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|s| {
+        for _ in 0..config.jobs.min(entries.len()) {
+            let tx = tx.clone();
+            let next = &next;
+            let filenames = &filenames;
+            s.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let filename = match filenames.get(i) {
+                    Some(Some(filename)) => filename,
+                    Some(None) => {
+                        let _ = tx.send((i, vec![], HashSet::new(), Ok(0)));
+                        continue;
+                    }
+                    None => break,
+                };
+                let mut buf = vec![];
+                let mut found = HashSet::new();
+                // エラーはスレッドをまたげないので文字列にしておく
+                let result = search_file(config, filename, show_filename, &mut buf, &mut found)
+                    .map_err(|e| e.to_string());
+                if tx.send((i, buf, found, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        let mut out = io::stdout().lock();
+        let mut pending = BTreeMap::new();
+        let mut printed = 0;
+        let mut total = 0;
+        for (i, buf, found, result) in rx {
+            pending.insert(i, (buf, found, result));
+            while let Some((buf, found, result)) = pending.remove(&printed) {
+                if let Err(e) = &entries[printed] {
+                    eprintln!("{}", e);
+                }
+                out.write_all(&buf)?;
+                distinct.extend(found);
+                // 読み込みエラーは逐次探索と同じくそこで終える
+                total += result?;
+                printed += 1;
+            }
         }
-        println!("{}", matches.len());
+        Ok(total)
+    })
+}
+
+// マッチした行の数を返す。--count-distinct ならマッチした文字列を distinct に集める
+fn search_file(
+    config: &Config,
+    filename: &str,
+    show_filename: bool,
+    out: &mut impl Write,
+    distinct: &mut HashSet<String>,
+) -> MyResult<usize> {
+    let mut file = match open(filename, config.byte_range.as_ref()) {
+        Err(e) => {
+            eprintln!("{}: {}", filename, e);
+            return Ok(0);
+        }
+        Ok(file) => file,
+    };
+    let binary = match config.binary_files {
+        BinaryFiles::Text if !config.text_only => false,
+        _ => is_binary(&mut file)?,
+    };
+    if binary && (config.text_only || config.binary_files == BinaryFiles::WithoutMatch) {
+        return Ok(0);
+    }
+    let list_files = config.files_with_matches || config.files_without_match;
+    let mut printer = Printer {
+        config,
+        filename: display_filename(filename, config.strip_prefix.as_deref()),
+        show_filename,
+        last_number: None,
+    };
+    // -c と -l/-L では数えるだけで行は出さない
+    let emit = |line: Line| -> MyResult<()> {
+        if let Some(longest) = config
+            .only_matching
+            .as_ref()
+            .filter(|_| config.count_distinct)
+        {
+            let text = line.text.trim_end_matches(['\r', '\n']);
+            for span in match_spans(text, &config.pattern, longest, config.overlapping) {
+                distinct.insert(text[span].to_string());
+            }
+        } else if !config.count && !list_files {
+            printer.print(out, &line)?;
+        }
+        Ok(())
+    };
+    let found = if binary {
+        // バイナリファイルは行を出さず、マッチしたことだけを知らせる
+        let max_count = if config.count {
+            config.max_count
+        } else {
+            Some(1)
+        };
+        let found = count_binary(file, &config.pattern, config.invert_match, max_count)?;
+        if found > 0 && !config.count && !list_files && !config.json && !config.count_distinct {
+            writeln!(out, "Binary file {} matches", printer.filename)?;
+        }
+        found
+    } else if config.passthru {
+        read_lines(file, emit)?
+    } else if config.until_match {
+        read_until_match(
+            file,
+            &config.pattern,
+            config.invert_match,
+            !config.exclusive,
+            emit,
+        )?
     } else {
-        matches.iter().for_each(|m| {
-            if show_filename {
-                print!("{}:", filename);
+        // -c と -l/-L では前後の行は要らない
+        let (before, after) = if config.count || list_files {
+            (0, 0)
+        } else {
+            (config.before_context, config.after_context)
+        };
+        // -l/-L はマッチがひとつ見つかれば十分なので、そこで読むのをやめる
+        let max_count = if list_files {
+            Some(1)
+        } else {
+            config.max_count
+        };
+        find_lines(
+            file,
+            &config.pattern,
+            config.invert_match,
+            before,
+            after,
+            max_count,
+            emit,
+        )?
+    };
+    if list_files {
+        if (found == 0) != config.files_with_matches {
+            writeln!(out, "{}", printer.filename)?;
+        }
+    } else if config.count {
+        if printer.show_filename {
+            write!(out, "{}:", printer.filename)?;
+        }
+        writeln!(out, "{}", found)?;
+    }
+    Ok(found)
+}
+
+// 1 ファイル分の出力の状態。行は見つかるたびに書き出す
+struct Printer<'a> {
+    config: &'a Config,
+    filename: &'a str,
+    show_filename: bool,
+    last_number: Option<usize>,
+}
+
+impl Printer<'_> {
+    fn print(&mut self, out: &mut impl Write, line: &Line) -> io::Result<()> {
+        if self.config.json {
+            self.print_json(out, line)
+        } else if let Some(replacement) = &self.config.replace_preview {
+            self.print_replace_preview(out, line, replacement)
+        } else if let Some(longest) = &self.config.only_matching {
+            self.print_only_matching(out, line, longest)
+        } else {
+            self.print_match(out, line)
+        }
+    }
+
+    fn print_match(&mut self, out: &mut impl Write, line: &Line) -> io::Result<()> {
+        let config = self.config;
+        // 連続していないグループの間には区切りを入れる
+        let has_context = config.before_context > 0 || config.after_context > 0;
+        if has_context && self.last_number.is_some_and(|n| n + 1 != line.number) {
+            writeln!(out, "--")?;
+        }
+        self.last_number = Some(line.number);
+        // 前後の行は ':' の代わりに '-' で区切る
+        let separator = if line.context { '-' } else { ':' };
+        if self.show_filename {
+            write!(out, "{}{}", self.filename, separator)?;
+        }
+        if config.line_number {
+            write!(out, "{}{}", line.number, separator)?;
+        }
+        if config.color && line.context && config.dim_context {
+            write!(out, "{}", dim(&line.text))
+        } else if config.color {
+            write!(out, "{}", highlight(&line.text, &config.pattern))
+        } else {
+            write!(out, "{}", line.text)
+        }
+    }
+
+    // マッチした部分だけを 1 行ずつ出す
+    fn print_only_matching(
+        &self,
+        out: &mut impl Write,
+        line: &Line,
+        longest: &meta::Regex,
+    ) -> io::Result<()> {
+        let config = self.config;
+        let text = line.text.trim_end_matches(['\r', '\n']);
+        for span in match_spans(text, &config.pattern, longest, config.overlapping) {
+            if self.show_filename {
+                write!(out, "{}:", self.filename)?;
+            }
+            if config.line_number {
+                write!(out, "{}:", line.number)?;
+            }
+            if config.color {
+                writeln!(out, "{}{}{}", COLOR_MATCH, &text[span], COLOR_RESET)?;
+            } else {
+                writeln!(out, "{}", &text[span])?;
             }
-            print!("{}", m)
+        }
+        Ok(())
+    }
+
+    fn print_json(&self, out: &mut impl Write, line: &Line) -> io::Result<()> {
+        if line.context {
+            return Ok(());
+        }
+        // 列番号は最初のマッチの位置 (1 始まり、バイト単位)。-v の場合は null
+        let column = self.config.pattern.find(&line.text).map(|m| m.start() + 1);
+        let value = serde_json::json!({
+            "file": self.filename,
+            "line_number": line.number,
+            "column": column,
+            "text": line.text.trim_end_matches(['\r', '\n']),
         });
+        writeln!(out, "{}", value)
+    }
+
+    // 置換で変わる行だけを "before => after" の形で出す
+    fn print_replace_preview(
+        &self,
+        out: &mut impl Write,
+        line: &Line,
+        replacement: &str,
+    ) -> io::Result<()> {
+        let before = line.text.trim_end_matches(['\r', '\n']);
+        let after = self.config.pattern.replace_all(before, replacement);
+        if after == before {
+            return Ok(());
+        }
+        if self.show_filename {
+            write!(out, "{}:", self.filename)?;
+        }
+        if self.config.line_number {
+            write!(out, "{}:", line.number)?;
+        }
+        writeln!(out, "{} => {}", before, after)
+    }
+}
+
+// -o で出す範囲。開始位置は最も左、終わりはそこから最も長く取る (leftmost-longest)。
+// overlapping でなければ前のマッチの終わりから、overlapping なら前の開始位置の次の文字から探す
+fn match_spans(
+    text: &str,
+    pattern: &Regex,
+    longest: &meta::Regex,
+    overlapping: bool,
+) -> Vec<Range<usize>> {
+    let mut spans = vec![];
+    let mut pos = 0;
+    while let Some(m) = pattern.find_at(text, pos) {
+        let start = m.start();
+        let input = Input::new(text).range(start..).anchored(Anchored::Yes);
+        let end = longest
+            .search_half(&input)
+            .map_or(m.end(), |half| half.offset().max(m.end()));
+        // 空文字へのマッチは出さない
+        if end > start {
+            spans.push(start..end);
+        }
+        pos = if overlapping || end == start {
+            start + text[start..].chars().next().map_or(1, char::len_utf8)
+        } else {
+            end
+        };
+        if pos > text.len() {
+            break;
+        }
+    }
+    spans
+}
+
+// UTF-8 として読めない行があっても止まらないよう、バイト列のまま読んで数える
+fn count_binary(
+    mut file: impl BufRead,
+    pattern: &Regex,
+    invert_match: bool,
+    max_count: Option<usize>,
+) -> MyResult<usize> {
+    let mut found = 0;
+    let mut buf = vec![];
+    while max_count.is_none_or(|max| found < max) && file.read_until(b'\n', &mut buf)? > 0 {
+        if pattern.is_match(&String::from_utf8_lossy(&buf)) != invert_match {
+            found += 1;
+        }
+        buf.clear();
+    }
+    Ok(found)
+}
+
+// 先頭のバッファに NUL があればバイナリとみなす
+fn is_binary(file: &mut impl BufRead) -> io::Result<bool> {
+    Ok(file.fill_buf()?.contains(&0))
+}
+
+fn highlight(line: &str, pattern: &Regex) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+    // 空文字へのマッチは色付けしない
+    for m in pattern.find_iter(line).filter(|m| !m.is_empty()) {
+        result.push_str(&line[last..m.start()]);
+        result.push_str(COLOR_MATCH);
+        result.push_str(m.as_str());
+        result.push_str(COLOR_RESET);
+        last = m.end();
+    }
+    result.push_str(&line[last..]);
+    result
+}
+
+fn dim(line: &str) -> String {
+    let text = line.trim_end_matches(['\r', '\n']);
+    // 改行の前で色を戻す
+    format!(
+        "{}{}{}{}",
+        COLOR_DIM,
+        text,
+        COLOR_RESET,
+        &line[text.len()..]
+    )
+}
+
+fn display_filename<'a>(filename: &'a str, prefix: Option<&str>) -> &'a str {
+    match prefix.map(|prefix| Path::new(filename).strip_prefix(prefix)) {
+        Some(Ok(stripped)) => stripped.to_str().unwrap_or(filename),
+        _ => filename,
+    }
+}
+
+fn open(filename: &str, byte_range: Option<&Range<u64>>) -> MyResult<Box<dyn BufRead>> {
+    match (filename, byte_range) {
+        ("-", None) => Ok(Box::new(BufReader::new(io::stdin()))),
+        ("-", Some(_)) => Err("--start-byte/--end-byte require a seekable file".into()),
+        (_, None) => Ok(Box::new(BufReader::new(File::open(filename)?))),
+        (_, Some(range)) => {
+            // 範囲内で始まる行だけを読む (行番号は範囲の先頭から数える)
+            let mut file = File::open(filename)?;
+            let len = file.metadata()?.len();
+            let start = snap_to_line(&mut file, range.start.min(len))?;
+            let end = snap_to_line(&mut file, range.end.min(len))?.max(start);
+            file.seek(SeekFrom::Start(start))?;
+            Ok(Box::new(BufReader::new(file.take(end - start))))
+        }
     }
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+// pos 以降で最初の行頭の位置を返す
+fn snap_to_line(file: &mut File, pos: u64) -> io::Result<u64> {
+    if pos == 0 {
+        return Ok(0);
     }
+    file.seek(SeekFrom::Start(pos - 1))?;
+    let skipped = BufReader::new(&mut *file).read_until(b'\n', &mut vec![])?;
+    Ok(pos - 1 + skipped as u64)
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -67,11 +506,35 @@ pub fn get_args() -> MyResult<Config> {
         .version("0.1.0")
         .author("SeeLog")
         .about("Rust grep")
+        // -h は --no-filename に使う
+        .disable_help_flag(true)
+        .arg(
+            Arg::new("help")
+                .long("help")
+                .action(ArgAction::Help)
+                .help("Print help"),
+        )
+        .arg(
+            Arg::new("with_filename")
+                .short('H')
+                .long("with-filename")
+                .action(ArgAction::SetTrue)
+                .overrides_with("no_filename")
+                .help("Print the file name for each match"),
+        )
+        .arg(
+            Arg::new("no_filename")
+                .short('h')
+                .long("no-filename")
+                .action(ArgAction::SetTrue)
+                .overrides_with("with_filename")
+                .help("Never print file names"),
+        )
         .arg(
             Arg::new("pattern")
                 .value_name("PATTERN")
                 .help("Search pattern")
-                .required(true)
+                .required_unless_present_any(["pattern_file", "regexp"])
                 .num_args(1),
         )
         .arg(
@@ -97,6 +560,13 @@ pub fn get_args() -> MyResult<Config> {
                 .action(ArgAction::SetTrue)
                 .help("Count occurrences"),
         )
+        .arg(
+            Arg::new("line_number")
+                .short('n')
+                .long("line-number")
+                .action(ArgAction::SetTrue)
+                .help("Prefix each line with its line number"),
+        )
         .arg(
             Arg::new("invert_match")
                 .value_name("INVERT")
@@ -113,55 +583,613 @@ pub fn get_args() -> MyResult<Config> {
                 .action(ArgAction::SetTrue)
                 .help("Case insensitive"),
         )
+        .arg(
+            Arg::new("smart_case")
+                .value_name("SMART_CASE")
+                .short('S')
+                .long("smart-case")
+                .action(ArgAction::SetTrue)
+                .help("Case insensitive unless the pattern contains uppercase"),
+        )
+        .arg(
+            Arg::new("strip_prefix")
+                .value_name("PATH")
+                .long("strip-prefix")
+                .num_args(1)
+                .help("Remove a leading path from displayed filenames"),
+        )
+        .arg(
+            Arg::new("regexp")
+                .value_name("PATTERN")
+                .short('e')
+                .long("regexp")
+                .num_args(1)
+                .action(ArgAction::Append)
+                .allow_hyphen_values(true)
+                .help("Search for PATTERN; may be given more than once"),
+        )
+        .arg(
+            Arg::new("pattern_file")
+                .value_name("FILE")
+                .short('f')
+                .long("file")
+                .num_args(1)
+                .action(ArgAction::Append)
+                .help("Read patterns from FILE, one per line"),
+        )
+        .arg(
+            Arg::new("fixed_strings")
+                .value_name("FIXED_STRINGS")
+                .short('F')
+                .long("fixed-strings")
+                .action(ArgAction::SetTrue)
+                .help("Treat patterns as literal strings"),
+        )
+        .arg(
+            Arg::new("color")
+                .value_name("WHEN")
+                .long("color")
+                .num_args(1)
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto")
+                .help("Highlight matches (auto: only when stdout is a terminal)"),
+        )
+        .arg(
+            Arg::new("passthru")
+                .long("passthru")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["count", "invert_match"])
+                .help("Print all lines, highlighting matches"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["count", "passthru"])
+                .help("Print matches as JSON lines"),
+        )
+        .arg(
+            Arg::new("start_byte")
+                .value_name("OFFSET")
+                .long("start-byte")
+                .num_args(1)
+                .value_parser(clap::value_parser!(u64))
+                .help("Only search lines starting at or after byte OFFSET"),
+        )
+        .arg(
+            Arg::new("end_byte")
+                .value_name("OFFSET")
+                .long("end-byte")
+                .num_args(1)
+                .value_parser(clap::value_parser!(u64))
+                .help("Only search lines starting before byte OFFSET"),
+        )
+        .arg(
+            Arg::new("files_with_matches")
+                .short('l')
+                .long("files-with-matches")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["count", "passthru", "json"])
+                .help("Print only names of files with matches"),
+        )
+        .arg(
+            Arg::new("files_without_match")
+                .short('L')
+                .long("files-without-match")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["count", "passthru", "json", "files_with_matches"])
+                .help("Print only names of files without matches"),
+        )
+        .arg(
+            Arg::new("text_only")
+                .long("text-only")
+                .action(ArgAction::SetTrue)
+                .requires("list_files")
+                .help("Exclude binary files from -l/-L output"),
+        )
+        .arg(
+            Arg::new("max_filesize")
+                .value_name("SIZE")
+                .long("max-filesize")
+                .num_args(1)
+                .value_parser(parse_size)
+                .help("Skip files larger than SIZE (suffixes K, M, G)"),
+        )
+        .arg(
+            Arg::new("after_context")
+                .value_name("NUM")
+                .short('A')
+                .long("after-context")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with("passthru")
+                .help("Print NUM lines of context after matches"),
+        )
+        .arg(
+            Arg::new("before_context")
+                .value_name("NUM")
+                .short('B')
+                .long("before-context")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with("passthru")
+                .help("Print NUM lines of context before matches"),
+        )
+        .arg(
+            Arg::new("context")
+                .value_name("NUM")
+                .short('C')
+                .long("context")
+                .num_args(1)
+                .default_value("0")
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with("passthru")
+                .help("Print NUM lines of context around matches"),
+        )
+        .arg(
+            Arg::new("dim_context")
+                .long("dim-context")
+                .action(ArgAction::SetTrue)
+                .help("Dim context lines when using colors"),
+        )
+        .arg(
+            Arg::new("max_count")
+                .value_name("NUM")
+                .short('m')
+                .long("max-count")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with("passthru")
+                .help("Stop reading a file after NUM matching lines"),
+        )
+        .arg(
+            Arg::new("until_match")
+                .long("until-match")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "count",
+                    "passthru",
+                    "files_with_matches",
+                    "files_without_match",
+                    "context",
+                    "after_context",
+                    "before_context",
+                    "max_count",
+                ])
+                .help("Print lines up to and including the first match, then stop"),
+        )
+        .arg(
+            Arg::new("exclusive")
+                .long("exclusive")
+                .action(ArgAction::SetTrue)
+                .requires("until_match")
+                .help("Leave out the matching line with --until-match"),
+        )
+        .arg(
+            Arg::new("replace_preview")
+                .value_name("REPLACEMENT")
+                .long("replace-preview")
+                .num_args(1)
+                .conflicts_with_all([
+                    "count",
+                    "invert_match",
+                    "passthru",
+                    "json",
+                    "list_files",
+                    "until_match",
+                    "context",
+                    "after_context",
+                    "before_context",
+                ])
+                .help("Show how each matching line would change as \"before => after\""),
+        )
+        .arg(
+            Arg::new("include")
+                .value_name("GLOB")
+                .long("include")
+                .num_args(1)
+                .action(ArgAction::Append)
+                .requires("recursive")
+                .help("With -r, only search files whose name matches GLOB"),
+        )
+        .arg(
+            Arg::new("exclude")
+                .value_name("GLOB")
+                .long("exclude")
+                .num_args(1)
+                .action(ArgAction::Append)
+                .requires("recursive")
+                .help("With -r, skip files and directories whose name matches GLOB"),
+        )
+        .arg(
+            Arg::new("jobs")
+                .value_name("N")
+                .short('j')
+                .long("jobs")
+                .num_args(1)
+                .default_value("1")
+                .value_parser(parse_jobs)
+                .help("Search N files at a time"),
+        )
+        .arg(
+            Arg::new("total")
+                .long("total")
+                .action(ArgAction::SetTrue)
+                .requires("count")
+                .help("With -c, also print the number of matching lines in all files"),
+        )
+        .arg(
+            Arg::new("only_matching")
+                .short('o')
+                .long("only-matching")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "invert_match",
+                    "passthru",
+                    "json",
+                    "replace_preview",
+                    "until_match",
+                    "context",
+                    "after_context",
+                    "before_context",
+                ])
+                .help("Print only the matched parts (leftmost-longest, non-overlapping)"),
+        )
+        .arg(
+            Arg::new("overlapping")
+                .long("overlapping")
+                .action(ArgAction::SetTrue)
+                .requires("only_matching")
+                .help("With -o, also print matches that overlap earlier ones"),
+        )
+        .arg(
+            Arg::new("count_distinct")
+                .long("count-distinct")
+                .action(ArgAction::SetTrue)
+                .requires("only_matching")
+                .conflicts_with_all(["count", "list_files"])
+                .help("With -o, print only the number of distinct matched strings"),
+        )
+        .arg(
+            Arg::new("binary_files")
+                .value_name("TYPE")
+                .long("binary-files")
+                .num_args(1)
+                .value_parser(["binary", "text", "without-match"])
+                .default_value("binary")
+                .help("How to handle files containing NUL bytes"),
+        )
+        .group(ArgGroup::new("list_files").args(["files_with_matches", "files_without_match"]))
         .get_matches();
 
-    let insensitive = matches.get_flag("insensitive");
-    let pattern_string = matches.get_one::<String>("pattern").unwrap();
-    let pattern = RegexBuilder::new(pattern_string)
-        .case_insensitive(insensitive)
-        .build()
-        .map_err(|_| format!("Invalid pattern \"{}\"", pattern_string))?;
-    let files = matches
+    let mut files: Vec<String> = matches
         .get_many::<String>("files")
         .unwrap()
         .map(|s| s.to_string())
         .collect();
+    let mut patterns: Vec<String> = matches
+        .get_many::<String>("regexp")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+    for pattern_file in matches
+        .get_many::<String>("pattern_file")
+        .unwrap_or_default()
+    {
+        let contents = std::fs::read_to_string(pattern_file)
+            .map_err(|e| format!("{}: {}", pattern_file, e))?;
+        patterns.extend(contents.lines().map(String::from));
+    }
+    if matches.contains_id("regexp") || matches.contains_id("pattern_file") {
+        // -e/-f 指定時は最初の位置引数もファイルとして扱う
+        if let Some(file) = matches.get_one::<String>("pattern") {
+            if matches.value_source("files") == Some(ValueSource::DefaultValue) {
+                files.clear();
+            }
+            files.insert(0, file.to_string());
+        }
+    } else {
+        patterns.push(matches.get_one::<String>("pattern").unwrap().to_string());
+    }
+    if matches.get_flag("fixed_strings") {
+        patterns = patterns.iter().map(|p| regex::escape(p)).collect();
+    }
+
+    let pattern_string = match patterns.len() {
+        // パターンが空なら何にもマッチしない
+        0 => r"[^\s\S]".to_string(),
+        1 => patterns[0].clone(),
+        _ => patterns
+            .iter()
+            .map(|p| format!("(?:{})", p))
+            .collect::<Vec<_>>()
+            .join("|"),
+    };
+    // -i が指定されていれば smart-case より優先する
+    let insensitive = matches.get_flag("insensitive")
+        || (matches.get_flag("smart_case") && !has_uppercase_literal(&pattern_string));
+    let pattern = RegexBuilder::new(&pattern_string)
+        .case_insensitive(insensitive)
+        .build()
+        .map_err(|_| format!("Invalid pattern \"{}\"", pattern_string))?;
+    let only_matching = if matches.get_flag("only_matching") {
+        let longest = meta::Regex::builder()
+            .configure(meta::Regex::config().match_kind(MatchKind::All))
+            .syntax(syntax::Config::new().case_insensitive(insensitive))
+            .build(&pattern_string)
+            .map_err(|_| format!("Invalid pattern \"{}\"", pattern_string))?;
+        Some(longest)
+    } else {
+        None
+    };
     let recursive = matches.get_flag("recursive");
     let count = matches.get_flag("count");
     let invert_match = matches.get_flag("invert_match");
+    let strip_prefix = matches
+        .get_one::<String>("strip_prefix")
+        .map(|s| s.replace('\\', "/"));
+
+    let color = match matches.get_one::<String>("color").unwrap().as_str() {
+        "always" => true,
+        "never" => false,
+        _ => io::stdout().is_terminal(),
+    };
+    let passthru = matches.get_flag("passthru");
+    let with_filename = if matches.get_flag("with_filename") {
+        Some(true)
+    } else if matches.get_flag("no_filename") {
+        Some(false)
+    } else {
+        None
+    };
+
+    // -A/-B が指定されていればそちらを -C より優先する
+    let context = *matches.get_one::<usize>("context").unwrap();
+    let before_context = matches
+        .get_one::<usize>("before_context")
+        .copied()
+        .unwrap_or(context);
+    let after_context = matches
+        .get_one::<usize>("after_context")
+        .copied()
+        .unwrap_or(context);
+
+    let start_byte = matches.get_one::<u64>("start_byte").copied();
+    let end_byte = matches.get_one::<u64>("end_byte").copied();
+    let byte_range = match (start_byte, end_byte) {
+        (None, None) => None,
+        (start, end) => {
+            let range = start.unwrap_or(0)..end.unwrap_or(u64::MAX);
+            if range.is_empty() {
+                return Err(format!(
+                    "--end-byte ({}) must be greater than --start-byte ({})",
+                    range.end, range.start
+                )
+                .into());
+            }
+            Some(range)
+        }
+    };
+    // 標準入力は読み飛ばせないので、警告で済ませずに最初にエラーにする
+    if byte_range.is_some() && files.iter().any(|file| file == "-") {
+        return Err("--start-byte/--end-byte require a seekable file".into());
+    }
 
     Ok(Config {
         pattern,
         files,
         recursive,
         count,
+        line_number: matches.get_flag("line_number"),
         invert_match,
+        strip_prefix,
+        color,
+        passthru,
+        json: matches.get_flag("json"),
+        byte_range,
+        files_with_matches: matches.get_flag("files_with_matches"),
+        files_without_match: matches.get_flag("files_without_match"),
+        text_only: matches.get_flag("text_only"),
+        max_filesize: matches.get_one::<u64>("max_filesize").copied(),
+        before_context,
+        after_context,
+        dim_context: matches.get_flag("dim_context"),
+        max_count: matches.get_one::<usize>("max_count").copied(),
+        until_match: matches.get_flag("until_match"),
+        exclusive: matches.get_flag("exclusive"),
+        replace_preview: matches.get_one::<String>("replace_preview").cloned(),
+        with_filename,
+        include: build_globs(&matches, "include")?,
+        exclude: build_globs(&matches, "exclude")?,
+        jobs: *matches.get_one::<usize>("jobs").unwrap(),
+        total: matches.get_flag("total"),
+        only_matching,
+        overlapping: matches.get_flag("overlapping"),
+        count_distinct: matches.get_flag("count_distinct"),
+        binary_files: match matches.get_one::<String>("binary_files").unwrap().as_str() {
+            "text" => BinaryFiles::Text,
+            "without-match" => BinaryFiles::WithoutMatch,
+            _ => BinaryFiles::Binary,
+        },
     })
 }
 
+// smart-case の判定では \S や \p{Lu} のようなエスケープの中の大文字は数えない
+fn has_uppercase_literal(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('p' | 'P') if chars.as_str().starts_with('{') => {
+                    chars.by_ref().find(|&c| c == '}');
+                }
+                // \pL のような 1 文字のクラス名
+                Some('p' | 'P') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            c if c.is_uppercase() => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn build_globs(matches: &clap::ArgMatches, id: &str) -> MyResult<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in matches.get_many::<String>(id).unwrap_or_default() {
+        builder.add(Glob::new(glob).map_err(|e| format!("--{}: {}", id, e))?);
+    }
+    Ok(builder.build()?)
+}
+
+fn parse_jobs(val: &str) -> Result<usize, String> {
+    match val.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("invalid number of jobs '{}'", val)),
+    }
+}
+
+fn parse_size(val: &str) -> Result<u64, String> {
+    let (num, unit) = match val.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => val.split_at(i),
+        None => (val, ""),
+    };
+    let multiplier = match unit.to_ascii_uppercase().as_str() {
+        "" => 1,
+        "K" => 1024,
+        "M" => 1024 * 1024,
+        "G" => 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid size '{}'", val)),
+    };
+    num.parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size '{}'", val))
+}
+
+// 見つかった行は溜めずに emit へ渡し、マッチした行の数を返す
 fn find_lines<T: BufRead>(
     mut file: T,
     pattern: &Regex,
     invert_match: bool,
-) -> MyResult<Vec<String>> {
-    let mut matches = vec![];
+    before_context: usize,
+    after_context: usize,
+    max_count: Option<usize>,
+    mut emit: impl FnMut(Line) -> MyResult<()>,
+) -> MyResult<usize> {
+    let mut found = 0;
+    // マッチの前に出す候補の行と、マッチの後にあと何行出すか
+    let mut before = VecDeque::new();
+    let mut after = 0;
     let mut line = String::new();
+    let mut line_number = 0;
 
     loop {
+        // 上限に達したら後ろの行を出し終えた時点で読むのをやめる。
+        // それまでに一致する行があっても、数えずに後ろの行として出す
+        let limit_reached = max_count.is_some_and(|max| found >= max);
+        if limit_reached && after == 0 {
+            break;
+        }
         let bytes = file.read_line(&mut line)?;
         if bytes == 0 {
             break;
         }
-        if pattern.is_match(&line) != invert_match {
-            matches.push(line.clone());
+        line_number += 1;
+        let text = std::mem::take(&mut line);
+        if !limit_reached && pattern.is_match(&text) != invert_match {
+            found += 1;
+            for line in before.drain(..) {
+                emit(line)?;
+            }
+            emit(Line {
+                number: line_number,
+                text,
+                context: false,
+            })?;
+            after = after_context;
+        } else if after > 0 {
+            after -= 1;
+            emit(Line {
+                number: line_number,
+                text,
+                context: true,
+            })?;
+        } else if before_context > 0 {
+            if before.len() == before_context {
+                before.pop_front();
+            }
+            before.push_back(Line {
+                number: line_number,
+                text,
+                context: true,
+            });
         }
-        line.clear();
     }
 
-    Ok(matches)
+    Ok(found)
+}
+
+fn read_lines<T: BufRead>(
+    mut file: T,
+    mut emit: impl FnMut(Line) -> MyResult<()>,
+) -> MyResult<usize> {
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    while file.read_line(&mut line)? > 0 {
+        line_number += 1;
+        emit(Line {
+            number: line_number,
+            text: std::mem::take(&mut line),
+            context: false,
+        })?;
+    }
+
+    Ok(line_number)
 }
 
-fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
+fn read_until_match<T: BufRead>(
+    mut file: T,
+    pattern: &Regex,
+    invert_match: bool,
+    inclusive: bool,
+    mut emit: impl FnMut(Line) -> MyResult<()>,
+) -> MyResult<usize> {
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    while file.read_line(&mut line)? > 0 {
+        let text = std::mem::take(&mut line);
+        let is_match = pattern.is_match(&text) != invert_match;
+        if is_match && !inclusive {
+            break;
+        }
+        line_number += 1;
+        emit(Line {
+            number: line_number,
+            text,
+            context: false,
+        })?;
+        if is_match {
+            break;
+        }
+    }
+
+    Ok(line_number)
+}
+
+fn find_files(
+    paths: &[String],
+    recursive: bool,
+    max_filesize: Option<u64>,
+    include: &GlobSet,
+    exclude: &GlobSet,
+) -> Vec<MyResult<String>> {
+    // --max-filesize より大きいファイルは黙って飛ばす
+    let small_enough = |len: u64| max_filesize.is_none_or(|max| len <= max);
     let mut files: Vec<MyResult<String>> = vec![];
     for path in paths {
         let path = path.replace("\\", "/");
@@ -177,13 +1205,19 @@ fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
 
         let metadata = metadata.unwrap();
         if metadata.is_file() {
-            files.push(Ok(path));
+            if small_enough(metadata.len()) {
+                files.push(Ok(path));
+            }
         } else if metadata.is_dir() && recursive {
+            // --exclude に合うディレクトリの中には入らない。起点は名前で除かない
             let ex_files = walkdir::WalkDir::new(&path)
                 .into_iter()
+                .filter_entry(|e| e.depth() == 0 || !exclude.is_match(e.file_name()))
                 .filter_map(|e| match e {
                     Ok(e) => {
-                        if e.path().is_file() {
+                        let len = e.metadata().map(|m| m.len()).unwrap_or_default();
+                        let included = include.is_empty() || include.is_match(e.file_name());
+                        if e.path().is_file() && included && small_enough(len) {
                             Some(e.path().display().to_string())
                         } else {
                             None
@@ -195,7 +1229,7 @@ fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
                     }
                 })
                 .collect::<Vec<String>>();
-            files.extend(ex_files.into_iter().map(|s| Ok(s)));
+            files.extend(ex_files.into_iter().map(Ok));
         } else if metadata.is_dir() {
             files.push(Err(format!("{} is a directory", path).into()));
         }
@@ -206,30 +1240,100 @@ fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
 
 #[cfg(test)]
 mod tests {
-    use std::io::Cursor;
+    use std::io::{BufRead, Cursor};
+
+    use crate::{
+        dim, display_filename, find_lines, has_uppercase_literal, highlight, is_binary,
+        match_spans, read_until_match, Line, MyResult, COLOR_DIM, COLOR_MATCH, COLOR_RESET,
+    };
+
+    // 渡された行を集めて返す
+    fn find_all(
+        file: impl BufRead,
+        pattern: &Regex,
+        invert_match: bool,
+        before_context: usize,
+        after_context: usize,
+        max_count: Option<usize>,
+    ) -> MyResult<Vec<Line>> {
+        let mut lines = vec![];
+        find_lines(
+            file,
+            pattern,
+            invert_match,
+            before_context,
+            after_context,
+            max_count,
+            |line| {
+                lines.push(line);
+                Ok(())
+            },
+        )?;
+        Ok(lines)
+    }
+
+    fn until_match_all(
+        file: impl BufRead,
+        pattern: &Regex,
+        invert_match: bool,
+        inclusive: bool,
+    ) -> MyResult<Vec<Line>> {
+        let mut lines = vec![];
+        read_until_match(file, pattern, invert_match, inclusive, |line| {
+            lines.push(line);
+            Ok(())
+        })?;
+        Ok(lines)
+    }
 
-    use crate::find_lines;
+    fn line(number: usize, text: &str, context: bool) -> Line {
+        Line {
+            number,
+            text: text.to_string(),
+            context,
+        }
+    }
 
-    use super::find_files;
-    use rand::{distributions::Alphanumeric, thread_rng, Rng};
+    use super::{find_files, parse_size};
+    use globset::{Glob, GlobSet, GlobSetBuilder};
+    use rand::{distributions::Alphanumeric, Rng};
     use regex::{Regex, RegexBuilder};
+    use regex_automata::{meta, MatchKind};
 
     #[test]
     fn test_find_files() {
         // 1個のファイルが探せる
-        let files = find_files(&["./tests/inputs/fox.txt".to_string()], false);
+        let files = find_files(
+            &["./tests/inputs/fox.txt".to_string()],
+            false,
+            None,
+            &GlobSet::empty(),
+            &GlobSet::empty(),
+        );
         assert_eq!(files.len(), 1);
         assert_eq!(files[0].as_ref().unwrap(), "./tests/inputs/fox.txt");
 
         // recursive なしの場合、ディレクトリはエラー
-        let files = find_files(&["./tests/inputs".to_string()], false);
+        let files = find_files(
+            &["./tests/inputs".to_string()],
+            false,
+            None,
+            &GlobSet::empty(),
+            &GlobSet::empty(),
+        );
         assert_eq!(files.len(), 1);
         if let Err(e) = &files[0] {
             assert_eq!(e.to_string(), "./tests/inputs is a directory");
         }
 
         // recursive ありの場合、ディレクトリ内を再帰的に探せる
-        let res = find_files(&["./tests/inputs".to_string()], true);
+        let res = find_files(
+            &["./tests/inputs".to_string()],
+            true,
+            None,
+            &GlobSet::empty(),
+            &GlobSet::empty(),
+        );
         let mut files: Vec<String> = res
             .iter()
             .map(|r| r.as_ref().unwrap().replace("\\", "/"))
@@ -254,25 +1358,164 @@ mod tests {
             .map(char::from)
             .collect();
 
-        let files = find_files(&[bad], false);
+        let files = find_files(&[bad], false, None, &GlobSet::empty(), &GlobSet::empty());
         assert_eq!(files.len(), 1);
         assert!(files[0].is_err());
     }
 
+    #[test]
+    fn test_find_files_max_filesize() {
+        // 上限より大きいファイルは明示しても再帰でも除く
+        let files = find_files(
+            &["./tests/sizes/large.txt".to_string()],
+            false,
+            Some(1024),
+            &GlobSet::empty(),
+            &GlobSet::empty(),
+        );
+        assert!(files.is_empty());
+
+        let files = find_files(
+            &["./tests/sizes".to_string()],
+            true,
+            Some(1024),
+            &GlobSet::empty(),
+            &GlobSet::empty(),
+        );
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].as_ref().unwrap(), "./tests/sizes/small.txt");
+
+        let files = find_files(
+            &["./tests/sizes".to_string()],
+            true,
+            None,
+            &GlobSet::empty(),
+            &GlobSet::empty(),
+        );
+        assert_eq!(files.len(), 2);
+    }
+
+    #[test]
+    fn test_find_files_globs() {
+        let globs = |patterns: &[&str]| {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                builder.add(Glob::new(pattern).unwrap());
+            }
+            builder.build().unwrap()
+        };
+        let find = |include: &[&str], exclude: &[&str]| {
+            let mut files: Vec<String> = find_files(
+                &["./tests/globs".to_string()],
+                true,
+                None,
+                &globs(include),
+                &globs(exclude),
+            )
+            .iter()
+            .map(|r| r.as_ref().unwrap().replace("\\", "/"))
+            .collect();
+            files.sort();
+            files
+        };
+
+        assert_eq!(
+            find(&["*.rs"], &[]),
+            ["./tests/globs/build/build.rs", "./tests/globs/src/main.rs"]
+        );
+        // ディレクトリ名に合えばその中は探さない
+        assert_eq!(find(&["*.rs"], &["build"]), ["./tests/globs/src/main.rs"]);
+        assert_eq!(
+            find(&[], &["*.txt", "src"]),
+            ["./tests/globs/build/build.rs"]
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("10"), Ok(10));
+        assert_eq!(parse_size("2K"), Ok(2048));
+        assert_eq!(parse_size("1m"), Ok(1024 * 1024));
+        assert_eq!(parse_size("3G"), Ok(3 * 1024 * 1024 * 1024));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("K").is_err());
+        assert!(parse_size("1KB").is_err());
+        assert!(parse_size("-1").is_err());
+    }
+
+    #[test]
+    fn test_display_filename() {
+        // プレフィックスなしはそのまま
+        assert_eq!(
+            display_filename("/home/me/proj/src/a.rs", None),
+            "/home/me/proj/src/a.rs"
+        );
+
+        // パスの先頭が一致すれば取り除く
+        assert_eq!(
+            display_filename("/home/me/proj/src/a.rs", Some("/home/me/proj")),
+            "src/a.rs"
+        );
+        assert_eq!(
+            display_filename("/home/me/proj/src/a.rs", Some("/home/me/proj/")),
+            "src/a.rs"
+        );
+
+        // コンポーネント単位でしか一致しない
+        assert_eq!(
+            display_filename("/home/me/project/a.rs", Some("/home/me/proj")),
+            "/home/me/project/a.rs"
+        );
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary(&mut Cursor::new(b"text\n")).unwrap());
+        assert!(is_binary(&mut Cursor::new(b"te\0xt\n")).unwrap());
+        assert!(!is_binary(&mut Cursor::new(b"")).unwrap());
+    }
+
+    #[test]
+    fn test_highlight() {
+        let re = Regex::new("o+").unwrap();
+        assert_eq!(
+            highlight("foo boo\n", &re),
+            format!("f{0}oo{1} b{0}oo{1}\n", COLOR_MATCH, COLOR_RESET)
+        );
+        assert_eq!(highlight("bar\n", &re), "bar\n");
+
+        // 空文字のマッチは無視する
+        let re = Regex::new("x*").unwrap();
+        assert_eq!(highlight("bar\n", &re), "bar\n");
+    }
+
+    #[test]
+    fn test_dim() {
+        assert_eq!(dim("bar\n"), format!("{}bar{}\n", COLOR_DIM, COLOR_RESET));
+        assert_eq!(
+            dim("bar\r\n"),
+            format!("{}bar{}\r\n", COLOR_DIM, COLOR_RESET)
+        );
+        assert_eq!(dim("bar"), format!("{}bar{}", COLOR_DIM, COLOR_RESET));
+    }
+
     #[test]
     fn test_find_lines() {
         let text = b"Lorem\nIpsum\r\nDOLOR";
 
         // or は Lorem にマッチ
         let rel = Regex::new("or").unwrap();
-        let matches = find_lines(Cursor::new(&&text), &rel, false);
+        let matches = find_all(Cursor::new(&&text), &rel, false, 0, 0, None);
         assert!(matches.is_ok());
-        assert_eq!(matches.unwrap().len(), 1);
+        assert_eq!(matches.unwrap(), vec![line(1, "Lorem\n", false)]);
 
         // invert_match ありの場合、Lorem 以外にマッチ
-        let matches = find_lines(Cursor::new(&&text), &rel, true);
+        let matches = find_all(Cursor::new(&&text), &rel, true, 0, 0, None);
         assert!(matches.is_ok());
-        assert_eq!(matches.unwrap().len(), 2);
+        assert_eq!(
+            matches.unwrap(),
+            vec![line(2, "Ipsum\r\n", false), line(3, "DOLOR", false)]
+        );
 
         // 大文字・小文字を区別しない
         let re2 = RegexBuilder::new("or")
@@ -281,13 +1524,207 @@ mod tests {
             .unwrap();
 
         // Lorem と DOLOR にマッチ
-        let matches = find_lines(Cursor::new(&&text), &re2, false);
+        let matches = find_all(Cursor::new(&&text), &re2, false, 0, 0, None);
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 2);
 
         // invert_match ありの場合、Lorem と DOLOR 以外にマッチ
-        let matches = find_lines(Cursor::new(&&text), &re2, true);
+        let matches = find_all(Cursor::new(&&text), &re2, true, 0, 0, None);
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 1);
     }
+
+    #[test]
+    fn test_match_spans() {
+        let spans = |pattern: &str, text: &str, overlapping: bool| {
+            let re = Regex::new(pattern).unwrap();
+            let longest = meta::Regex::builder()
+                .configure(meta::Regex::config().match_kind(MatchKind::All))
+                .build(pattern)
+                .unwrap();
+            match_spans(text, &re, &longest, overlapping)
+        };
+
+        // 重ならないように左から取る
+        assert_eq!(spans("a.a", "aaaaa", false), vec![0..3]);
+        // 重なりを許すと 1 文字ずつずらして探す
+        assert_eq!(spans("a.a", "aaaaa", true), vec![0..3, 1..4, 2..5]);
+        // 選択肢は先に書いたものではなく最も長いものを取る
+        assert_eq!(spans("a|ab|abc", "xabcx ab", false), vec![1..4, 6..8]);
+        assert_eq!(spans("a+?", "aaa", false), vec![0..3]);
+        // 空文字へのマッチは出さない
+        assert_eq!(spans("x*", "axb", false), vec![1..2]);
+        assert_eq!(spans("é", "éé", true), vec![0..2, 2..4]);
+    }
+
+    #[test]
+    fn test_find_lines_streams() {
+        let re = Regex::new("X").unwrap();
+        // 読み込みエラーの前に見つかった行はもう渡されている
+        let text = b"X\na\nX\n\xff\n";
+        let mut lines = vec![];
+        let result = find_lines(Cursor::new(&text), &re, false, 0, 1, None, |line| {
+            lines.push(line);
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(
+            lines,
+            vec![
+                line(1, "X\n", false),
+                line(2, "a\n", true),
+                line(3, "X\n", false)
+            ]
+        );
+
+        // 戻り値は前後の行を除いたマッチの数
+        let found = find_lines(
+            Cursor::new(b"X\na\nX\n"),
+            &re,
+            false,
+            1,
+            1,
+            None,
+            |_| Ok(()),
+        );
+        assert_eq!(found.unwrap(), 2);
+
+        // emit のエラーはそのまま返す
+        let result = find_lines(Cursor::new(b"X\n"), &re, false, 0, 0, None, |_| {
+            Err("stop".into())
+        });
+        assert_eq!(result.unwrap_err().to_string(), "stop");
+    }
+
+    #[test]
+    fn test_find_lines_invert_count() {
+        let re = Regex::new("X").unwrap();
+        let count = |text: &[u8], invert_match: bool| {
+            find_lines(Cursor::new(text), &re, invert_match, 0, 0, None, |_| Ok(())).unwrap()
+        };
+        // 空のファイルは反転しても 0
+        assert_eq!(count(b"", false), 0);
+        assert_eq!(count(b"", true), 0);
+        // マッチしない行だけなら、反転すると全行
+        assert_eq!(count(b"a\nb\nc", false), 0);
+        assert_eq!(count(b"a\nb\nc", true), 3);
+        // 1 行に何度マッチしても 1 行として数え、反転側には数えない
+        assert_eq!(count(b"XX X\na\n", false), 1);
+        assert_eq!(count(b"XX X\na\n", true), 1);
+    }
+
+    #[test]
+    fn test_has_uppercase_literal() {
+        assert!(!has_uppercase_literal("the"));
+        assert!(has_uppercase_literal("The"));
+        assert!(!has_uppercase_literal(r"\Sfoo\W\D\B"));
+        assert!(!has_uppercase_literal(r"\p{Lu}x\PL"));
+        assert!(has_uppercase_literal(r"\\Foo"));
+        assert!(has_uppercase_literal(r"\SFoo"));
+    }
+
+    #[test]
+    fn test_find_lines_max_count() {
+        let re = Regex::new("X").unwrap();
+        // 上限より後ろは読まないので、不正な UTF-8 があってもエラーにならない
+        let text = b"X\na\nX\nX\n\xff\n";
+        let matches = find_all(Cursor::new(&text), &re, false, 0, 0, Some(2)).unwrap();
+        assert_eq!(matches, vec![line(1, "X\n", false), line(3, "X\n", false)]);
+        assert!(find_all(Cursor::new(&text), &re, false, 0, 0, None).is_err());
+
+        // -v では反転したマッチを数える
+        let matches = find_all(Cursor::new(&text), &re, true, 0, 0, Some(1)).unwrap();
+        assert_eq!(matches, vec![line(2, "a\n", false)]);
+
+        // 後ろの行は出し切る
+        let matches = find_all(Cursor::new(&text), &re, false, 0, 1, Some(1)).unwrap();
+        assert_eq!(matches, vec![line(1, "X\n", false), line(2, "a\n", true)]);
+
+        // 上限に達した後の一致する行は数えず、後ろの行として出す
+        let matches = find_all(Cursor::new(b"X\nX\na\n"), &re, false, 0, 2, Some(1)).unwrap();
+        assert_eq!(
+            matches,
+            vec![
+                line(1, "X\n", false),
+                line(2, "X\n", true),
+                line(3, "a\n", true)
+            ]
+        );
+
+        let matches = find_all(Cursor::new(&text), &re, false, 0, 0, Some(0)).unwrap();
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_read_until_match() {
+        let re = Regex::new("^\\[").unwrap();
+        // 最初のマッチより後ろは読まない
+        let text = b"# header\nkey = 1\n[section]\n\xff\n";
+        let lines = until_match_all(Cursor::new(&text), &re, false, true).unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                line(1, "# header\n", false),
+                line(2, "key = 1\n", false),
+                line(3, "[section]\n", false),
+            ]
+        );
+
+        let lines = until_match_all(Cursor::new(&text), &re, false, false).unwrap();
+        assert_eq!(
+            lines,
+            vec![line(1, "# header\n", false), line(2, "key = 1\n", false)]
+        );
+
+        // マッチがなければすべての行
+        let lines = until_match_all(Cursor::new(b"a\nb\n"), &re, false, true).unwrap();
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn test_find_lines_context() {
+        let text = b"a\nb\nX\nc\nd\ne\nX\nf\n";
+        let re = Regex::new("X").unwrap();
+
+        // 前後 1 行ずつ
+        let matches = find_all(Cursor::new(&text), &re, false, 1, 1, None).unwrap();
+        assert_eq!(
+            matches,
+            vec![
+                line(2, "b\n", true),
+                line(3, "X\n", false),
+                line(4, "c\n", true),
+                line(6, "e\n", true),
+                line(7, "X\n", false),
+                line(8, "f\n", true),
+            ]
+        );
+
+        // 前後の範囲が重なる場合は同じ行を 2 回出さない
+        let matches = find_all(Cursor::new(&text), &re, false, 2, 2, None).unwrap();
+        let numbers: Vec<usize> = matches.iter().map(|l| l.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        // 後ろだけ、前だけ
+        let matches = find_all(Cursor::new(&text), &re, false, 0, 1, None).unwrap();
+        let numbers: Vec<usize> = matches.iter().map(|l| l.number).collect();
+        assert_eq!(numbers, vec![3, 4, 7, 8]);
+
+        let matches = find_all(Cursor::new(&text), &re, false, 3, 0, None).unwrap();
+        let numbers: Vec<usize> = matches.iter().map(|l| l.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5, 6, 7]);
+
+        // 後ろの範囲の途中で次のマッチがあれば、そこから数え直す
+        let text = b"X\na\nX\nb\nc\nd\n";
+        let matches = find_all(Cursor::new(&text), &re, false, 0, 2, None).unwrap();
+        assert_eq!(
+            matches,
+            vec![
+                line(1, "X\n", false),
+                line(2, "a\n", true),
+                line(3, "X\n", false),
+                line(4, "b\n", true),
+                line(5, "c\n", true),
+            ]
+        );
+    }
 }
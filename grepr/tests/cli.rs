@@ -280,3 +280,857 @@ fn stdin_insensitive_count() -> Result<()> {
     assert_eq!(stdout, expected);
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn bustle_smart_case_lowercase() -> Result<()> {
+    run(
+        &["--smart-case", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.lowercase.insensitive",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_smart_case_uppercase() -> Result<()> {
+    run(
+        &["-S", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn smart_case_ignores_escapes() -> Result<()> {
+    // \S は大文字の文字ではないので、大文字小文字を区別しない
+    Command::cargo_bin(PRG)?
+        .args(["-S", r"\Sfoo"])
+        .write_stdin("xFOO\nxfoo\n foo\n")
+        .assert()
+        .success()
+        .stdout("xFOO\nxfoo\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_smart_case_overridden_by_insensitive() -> Result<()> {
+    run(
+        &["-S", "-i", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.lowercase.insensitive",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_strip_prefix() -> Result<()> {
+    let expected = fs::read_to_string("tests/expected/dog.recursive")?.replace("tests/inputs/", "");
+    Command::cargo_bin(PRG)?
+        .args(["--strip-prefix", INPUTS_DIR, "-r", "dog", INPUTS_DIR])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_file_regex() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "tests/patterns/meta.txt"])
+        .write_stdin("a.b\naxb\n(x)\nx\ny\n")
+        .assert()
+        .success()
+        .stdout("a.b\naxb\n(x)\nx\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_file_fixed_strings() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-F", "-f", "tests/patterns/meta.txt"])
+        .write_stdin("a.b\naxb\n(x)\nx\ny\n")
+        .assert()
+        .success()
+        .stdout("a.b\n(x)\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_file_with_files() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "tests/patterns/meta.txt", FOX, EMPTY])
+        .assert()
+        .success()
+        .stdout("tests/inputs/fox.txt:The quick brown fox jumps over the lazy dog.\n");
+    Command::cargo_bin(PRG)?
+        .args(["-F", "-f", "tests/patterns/meta.txt", FOX, EMPTY])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn passthru() -> Result<()> {
+    let input = fs::read_to_string(BUSTLE)?;
+    let expected = input.replace("the", "\x1b[1;31mthe\x1b[0m");
+    Command::cargo_bin(PRG)?
+        .args(["--passthru", "--color", "always", "the", BUSTLE])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn passthru_never_color() -> Result<()> {
+    let input = fs::read_to_string(BUSTLE)?;
+    Command::cargo_bin(PRG)?
+        .args(["--passthru", "--color", "never", "the", BUSTLE])
+        .assert()
+        .success()
+        .stdout(input);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn json() -> Result<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(["--json", "dog", FOX, EMPTY])
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1);
+
+    let value: serde_json::Value = serde_json::from_str(lines[0])?;
+    assert_eq!(value["file"], FOX);
+    assert_eq!(value["line_number"], 1);
+    assert_eq!(value["column"], 41);
+    assert_eq!(
+        value["text"],
+        "The quick brown fox jumps over the lazy dog."
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn json_lines() -> Result<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(["--json", "-i", "the", BUSTLE])
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    let expected = fs::read_to_string("tests/expected/bustle.txt.the.lowercase.insensitive")?;
+    let values = stdout
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    let texts: Vec<&str> = values.iter().map(|v| v["text"].as_str().unwrap()).collect();
+    assert_eq!(texts, expected.lines().collect::<Vec<_>>());
+    assert!(values
+        .iter()
+        .all(|v| v["line_number"].is_u64() && v["column"].is_u64()));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn byte_range() -> Result<()> {
+    // 30 は 2 行目の途中、80 は 4 行目の途中
+    Command::cargo_bin(PRG)?
+        .args([".", BUSTLE, "--start-byte", "30", "--end-byte", "80"])
+        .assert()
+        .success()
+        .stdout("Is solemnest of industries\nEnacted upon earth,—\n");
+
+    // 行頭ちょうどの位置は含む
+    Command::cargo_bin(PRG)?
+        .args(["-c", ".", BUSTLE, "--start-byte", "22", "--end-byte", "73"])
+        .assert()
+        .success()
+        .stdout("2\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["Until", BUSTLE, "--start-byte", "124"])
+        .assert()
+        .success()
+        .stdout("Until eternity.\n");
+    Command::cargo_bin(PRG)?
+        .args(["Until", BUSTLE, "--end-byte", "124"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_byte_range_stdin() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["fox", "--start-byte", "1"])
+        .write_stdin("fox\n")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::contains("require a seekable file"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_byte_range() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["fox", FOX, "--start-byte", "10", "--end-byte", "10"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--end-byte (10) must be greater than --start-byte (10)",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files_with_matches() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-r", "-l", "fox", "tests/binary"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut lines: Vec<String> = stdout.lines().map(|l| l.replace('\\', "/")).collect();
+    lines.sort();
+    assert_eq!(lines, ["tests/binary/data.bin", "tests/binary/text.txt"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files_with_matches_text_only() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-r", "-l", "--text-only", "fox", "tests/binary"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout.replace('\\', "/"), "tests/binary/text.txt\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_text_only_without_l() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--text-only", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--files-with-matches"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn context() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-C", "1", "X"])
+        .write_stdin("a\nb\nX\nc\nd\ne\nX\nf\n")
+        .assert()
+        .success()
+        .stdout("b\nX\nc\n--\ne\nX\nf\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dim_context() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-C", "1", "--dim-context", "--color", "always", "X"])
+        .write_stdin("a\nX\nb\n")
+        .assert()
+        .success()
+        .stdout("\x1b[2ma\x1b[0m\n\x1b[1;31mX\x1b[0m\n\x1b[2mb\x1b[0m\n");
+
+    // --color=never なら色を付けない
+    Command::cargo_bin(PRG)?
+        .args(["-C", "1", "--dim-context", "--color", "never", "X"])
+        .write_stdin("a\nX\nb\n")
+        .assert()
+        .success()
+        .stdout("a\nX\nb\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn line_number() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "The", BUSTLE])
+        .assert()
+        .success()
+        .stdout(
+            "1:The bustle in a house\n2:The morning after death\n6:The sweeping up the heart,\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn line_number_invert_match() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-v", "e", BUSTLE])
+        .assert()
+        .success()
+        .stdout("5:\n");
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-v", "The", BUSTLE, FOX])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!(
+            "{}:3:Is solemnest of industries\n{}:4:",
+            BUSTLE, BUSTLE
+        )));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn line_number_ignored_with_count() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-c", "The", BUSTLE])
+        .assert()
+        .success()
+        .stdout("3\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn line_number_context() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-C", "1", "X"])
+        .write_stdin("a\nX\nb\nc\n")
+        .assert()
+        .success()
+        .stdout("1-a\n2:X\n3-b\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_filesize() -> Result<()> {
+    // 大きいファイルが除かれる
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-r", "fox", "tests/sizes", "--max-filesize", "1K"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(
+        stdout.replace('\\', "/"),
+        "tests/sizes/small.txt:small fox\n"
+    );
+
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-r", "-c", "fox", "tests/sizes"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout.lines().count(), 2);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn after_before_context() -> Result<()> {
+    let input = "a\nb\nX\nc\nd\ne\nf\nX\ng\n";
+    Command::cargo_bin(PRG)?
+        .args(["-A", "1", "X"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("X\nc\n--\nX\ng\n");
+    Command::cargo_bin(PRG)?
+        .args(["-B", "2", "X"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("a\nb\nX\n--\ne\nf\nX\n");
+
+    // -A/-B は -C より優先する
+    Command::cargo_bin(PRG)?
+        .args(["-C", "2", "-A", "0", "X"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("a\nb\nX\n--\ne\nf\nX\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn context_overlap_merges() -> Result<()> {
+    // 重なる範囲はひとつのグループになり、区切りも重複もない
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-A", "2", "-B", "1", "X"])
+        .write_stdin("a\nX\nb\nX\nc\nd\ne\n")
+        .assert()
+        .success()
+        .stdout("1-a\n2:X\n3-b\n4:X\n5-c\n6-d\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_count() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-m", "2", "-n", "X"])
+        .write_stdin("X1\na\nX2\nX3\nb\n")
+        .assert()
+        .success()
+        .stdout("1:X1\n3:X2\n");
+    Command::cargo_bin(PRG)?
+        .args(["-m", "1", "-c", "X"])
+        .write_stdin("X1\na\nX2\n")
+        .assert()
+        .success()
+        .stdout("1\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn until_match() -> Result<()> {
+    let input = "# header\nkey = 1\n[section]\nother = 2\n[next]\n";
+    Command::cargo_bin(PRG)?
+        .args(["--until-match", r"^\["])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("# header\nkey = 1\n[section]\n");
+    Command::cargo_bin(PRG)?
+        .args(["--until-match", "--exclusive", r"^\["])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("# header\nkey = 1\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files_with_and_without_match() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-r", "-l", "-i", "the", "tests/inputs"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut lines: Vec<String> = stdout.lines().map(|l| l.replace('\\', "/")).collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [
+            "tests/inputs/bustle.txt",
+            "tests/inputs/fox.txt",
+            "tests/inputs/nobody.txt"
+        ]
+    );
+
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-r", "-L", "-i", "the", "tests/inputs"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout.replace('\\', "/"), "tests/inputs/empty.txt\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files_with_and_without_match() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-L", "fox", FOX])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color_matches() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--color", "always", "-i", "fox", FOX])
+        .assert()
+        .success()
+        .stdout("The quick brown \x1b[1;31mfox\x1b[0m jumps over the lazy dog.\n");
+    Command::cargo_bin(PRG)?
+        .args(["--color", "never", "-i", "fox", FOX])
+        .assert()
+        .success()
+        .stdout("The quick brown fox jumps over the lazy dog.\n");
+
+    // auto では端末でない出力に色を付けない
+    Command::cargo_bin(PRG)?
+        .args(["-i", "fox", FOX])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn replace_preview() -> Result<()> {
+    // マッチしても置換で変わらない行は出さない
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--replace-preview", "color", "colou?r"])
+        .write_stdin("the colour red\nthe color blue\nno match\nColour and colour\n")
+        .assert()
+        .success()
+        .stdout(
+            "1:the colour red => the color red\n\
+             4:Colour and colour => Colour and color\n",
+        );
+
+    Command::cargo_bin(PRG)?
+        .args(["--replace-preview", "[$1]", r"(\w+)ing", BUSTLE])
+        .assert()
+        .success()
+        .stdout(
+            "The morning after death => The [morn] after death\n\
+             The sweeping up the heart, => The [sweep] up the heart,\n\
+             And putting love away => And [putt] love away\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_regexp() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-e", "FOX", "-e", "^x", "-i"])
+        .write_stdin("a fox\nxylophone\nnothing\nX marks\n")
+        .assert()
+        .success()
+        .stdout("a fox\nxylophone\nX marks\n");
+
+    // -e と -f の組み合わせ。最初の位置引数はファイルになる
+    Command::cargo_bin(PRG)?
+        .args([
+            "-i",
+            "-e",
+            "BUSTLE",
+            "-f",
+            "tests/patterns/meta.txt",
+            BUSTLE,
+            FOX,
+        ])
+        .assert()
+        .success()
+        .stdout(
+            "tests/inputs/bustle.txt:The bustle in a house\n\
+             tests/inputs/fox.txt:The quick brown fox jumps over the lazy dog.\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fixed_strings() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-F", "a.b"])
+        .write_stdin("a.b\naxb\nA.B\n")
+        .assert()
+        .success()
+        .stdout("a.b\n");
+    Command::cargo_bin(PRG)?
+        .args(["-F", "-i", "a.b"])
+        .write_stdin("a.b\naxb\nA.B\n")
+        .assert()
+        .success()
+        .stdout("a.b\nA.B\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn large_input_streams() -> Result<()> {
+    let input: String = (0..200_000)
+        .map(|i| format!("line {}{}\n", i, if i % 2 == 0 { " match" } else { "" }))
+        .collect();
+    let cmd = Command::cargo_bin(PRG)?
+        .arg("match")
+        .write_stdin(input.clone())
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout.lines().count(), 100_000);
+    assert!(stdout.starts_with("line 0 match\nline 2 match\n"));
+    assert!(stdout.ends_with("line 199998 match\n"));
+
+    Command::cargo_bin(PRG)?
+        .args(["-c", "match"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("100000\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn with_filename() -> Result<()> {
+    // -r なら 1 ファイルでもファイル名を付ける
+    Command::cargo_bin(PRG)?
+        .args(["-r", "fox", FOX])
+        .assert()
+        .success()
+        .stdout("tests/inputs/fox.txt:The quick brown fox jumps over the lazy dog.\n");
+    Command::cargo_bin(PRG)?
+        .args(["-H", "fox", FOX])
+        .assert()
+        .success()
+        .stdout("tests/inputs/fox.txt:The quick brown fox jumps over the lazy dog.\n");
+
+    // -h なら複数ファイルでも付けない
+    Command::cargo_bin(PRG)?
+        .args(["-h", "-c", "fox", FOX, EMPTY])
+        .assert()
+        .success()
+        .stdout("1\n0\n");
+    Command::cargo_bin(PRG)?
+        .args(["-h", "-r", "fox", FOX])
+        .assert()
+        .success()
+        .stdout("The quick brown fox jumps over the lazy dog.\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn include_exclude() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args([
+            "-r",
+            "--include",
+            "*.rs",
+            "--exclude",
+            "build",
+            "fox",
+            "tests/globs",
+        ])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(
+        stdout.replace('\\', "/"),
+        "tests/globs/src/main.rs:fox in main\n"
+    );
+
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-r", "-l", "--exclude", "*.rs", "fox", "tests/globs"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout.replace('\\', "/"), "tests/globs/notes.txt\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_glob() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-r", "--include", "[", "fox", "tests/globs"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--include"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn jobs_matches_sequential() -> Result<()> {
+    for args in [
+        &["-r", "-n", "-i", "the", INPUTS_DIR][..],
+        &["-c", "-i", "the", BUSTLE, EMPTY, FOX, NOBODY],
+        &["-r", "-l", "fox", "tests"],
+    ] {
+        let sequential = Command::cargo_bin(PRG)?.args(args).output()?;
+        for jobs in ["2", "8"] {
+            let parallel = Command::cargo_bin(PRG)?
+                .args(args)
+                .args(["-j", jobs])
+                .output()?;
+            assert!(parallel.status.success());
+            assert_eq!(parallel.stdout, sequential.stdout);
+        }
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_jobs() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-j", "0", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid number of jobs '0'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn count_total() -> Result<()> {
+    // マッチしないファイルも 0 と出す
+    let expected = "tests/inputs/bustle.txt:3\n\
+                    tests/inputs/empty.txt:0\n\
+                    tests/inputs/fox.txt:1\n\
+                    total:4\n";
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--total", "-i", "the", BUSTLE, EMPTY, FOX])
+        .assert()
+        .success()
+        .stdout(expected);
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--total", "-j", "3", "-i", "the", BUSTLE, EMPTY, FOX])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    // -v では反転したマッチを数える
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--total", "-v", "-i", "the", BUSTLE, EMPTY, FOX])
+        .assert()
+        .success()
+        .stdout(
+            "tests/inputs/bustle.txt:6\n\
+             tests/inputs/empty.txt:0\n\
+             tests/inputs/fox.txt:0\n\
+             total:6\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn only_matching() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-o", "-n", "a.a"])
+        .write_stdin("aaaaa\nbab\naXa aYa\n")
+        .assert()
+        .success()
+        .stdout("1:aaa\n3:aXa\n3:aYa\n");
+    Command::cargo_bin(PRG)?
+        .args(["-o", "--overlapping", "a.a"])
+        .write_stdin("aaaaa\n")
+        .assert()
+        .success()
+        .stdout("aaa\naaa\naaa\n");
+    // 大文字・小文字を区別しない場合も最長のものを取る
+    Command::cargo_bin(PRG)?
+        .args(["-o", "-i", "fo|fox|f"])
+        .write_stdin("The FOX\n")
+        .assert()
+        .success()
+        .stdout("FOX\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn count_distinct() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-o", "--count-distinct", r"\w"])
+        .write_stdin("a a b a\n")
+        .assert()
+        .success()
+        .stdout("2\n");
+    // ファイルをまたいでも同じ文字列は 1 つと数える
+    Command::cargo_bin(PRG)?
+        .args(["-o", "--count-distinct", "-j", "2", "(?i)the", BUSTLE, FOX])
+        .assert()
+        .success()
+        .stdout("2\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn count_invert_recursive() -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-r", "-c", "-v", "zzz", INPUTS_DIR])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut lines: Vec<String> = stdout.lines().map(|l| l.replace('\\', "/")).collect();
+    lines.sort();
+    // 空のファイルは 0、マッチしない行だけのファイルは全行数
+    assert_eq!(
+        lines,
+        [
+            "tests/inputs/bustle.txt:9",
+            "tests/inputs/empty.txt:0",
+            "tests/inputs/fox.txt:1",
+            "tests/inputs/nobody.txt:9",
+        ]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_files() -> Result<()> {
+    let data = "tests/binary/data.bin";
+    let latin = "tests/encoding/latin.bin";
+    let text = "tests/binary/text.txt";
+
+    // 既定ではバイナリファイルの行は出さない。UTF-8 でなくても止まらない
+    Command::cargo_bin(PRG)?
+        .args(["fox", data, latin, text])
+        .assert()
+        .success()
+        .stdout(
+            "Binary file tests/binary/data.bin matches\n\
+             Binary file tests/encoding/latin.bin matches\n\
+             tests/binary/text.txt:fox data\n",
+        );
+
+    Command::cargo_bin(PRG)?
+        .args(["--binary-files", "without-match", "fox", data, latin, text])
+        .assert()
+        .success()
+        .stdout("tests/binary/text.txt:fox data\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["--binary-files", "text", "fox", data])
+        .assert()
+        .success()
+        .stdout("fox\0\x01\x02 data\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_files_count_and_list() -> Result<()> {
+    // UTF-8 として読めないファイルも -c や -l で扱える
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-v", "zzz", "tests/encoding/latin.bin"])
+        .assert()
+        .success()
+        .stdout("2\n");
+    Command::cargo_bin(PRG)?
+        .args(["-l", "fox", "tests/encoding/latin.bin"])
+        .assert()
+        .success()
+        .stdout("tests/encoding/latin.bin\n");
+    Ok(())
+}
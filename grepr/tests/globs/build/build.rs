@@ -0,0 +1 @@
+fox in build output
@@ -1,34 +1,60 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 use std::{
+    collections::VecDeque,
     error::Error,
     fs::File,
-    io::{self, BufRead, BufReader},
+    io::{self, BufRead, BufReader, Read, Write},
 };
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+// -n/-c の数。先頭に - が付いていれば末尾の N 個を除いた全部
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Count {
+    First(usize),
+    AllButLast(usize),
+}
+
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    lines: usize,
-    bytes: Option<usize>,
+    lines: Count,
+    bytes: Option<Count>,
+    count_only: bool,
+    keep_header: bool,
+    // None なら複数ファイルのときだけ "==> file <==" を出す
+    headers: Option<bool>,
+    zero_terminated: bool,
 }
 
 pub fn run(config: Config) -> MyResult<()> {
+    // ヘッダー行は -n の行数に含めない
+    let lines = match config.lines {
+        Count::First(n) if config.keep_header => Count::First(n.saturating_add(1)),
+        lines => lines,
+    };
+    // -z なら行の区切りは NUL
+    let terminator = if config.zero_terminated { b'\0' } else { b'\n' };
     for (i, filename) in config.files.iter().enumerate() {
         match open(filename) {
             Err(e) => eprintln!("headr: {}: {}", filename, e),
             Ok(stream) => {
-                if config.files.len() > 1 {
+                if config.headers.unwrap_or(config.files.len() > 1) {
                     if i > 0 {
                         println!();
                     }
                     println!("==> {} <==", filename);
                 }
-                if let Some(bytes) = config.bytes {
-                    show_bytes(stream, bytes)?;
+                if config.count_only {
+                    let count = match config.bytes {
+                        Some(bytes) => count_bytes(stream, bytes)?,
+                        None => count_lines(stream, lines, terminator)?,
+                    };
+                    println!("{}", count);
+                } else if let Some(bytes) = config.bytes {
+                    show_bytes(stream, bytes, filename)?;
                 } else {
-                    show_lines(stream, config.lines)?;
+                    show_lines(stream, lines, terminator)?;
                 }
             }
         }
@@ -36,25 +62,53 @@ pub fn run(config: Config) -> MyResult<()> {
     Ok(())
 }
 
-fn show_lines(mut reader: Box<dyn BufRead>, lines: usize) -> MyResult<()> {
-    for _ in 0..lines {
-        let mut line = String::new();
-        let bytes = reader.read_line(&mut line);
-        if bytes? == 0 {
-            break;
-        }
+// 行は区切り文字ごとそのまま出す
+fn show_lines(mut reader: Box<dyn BufRead>, lines: Count, terminator: u8) -> MyResult<()> {
+    let mut out = io::stdout().lock();
+    match lines {
+        Count::First(lines) => {
+            for _ in 0..lines {
+                let mut line = vec![];
+                if reader.read_until(terminator, &mut line)? == 0 {
+                    break;
+                }
 
-        print!("{}", line);
+                out.write_all(&line)?;
+            }
+        }
+        Count::AllButLast(lines) => {
+            // 末尾の lines 行だけを持っておき、あふれた行から出していく。
+            // lines は巨大な値もありうるので先に確保しない
+            let mut tail = VecDeque::new();
+            loop {
+                let mut line = vec![];
+                if reader.read_until(terminator, &mut line)? == 0 {
+                    break;
+                }
+                tail.push_back(line);
+                if tail.len() > lines {
+                    out.write_all(&tail.pop_front().unwrap())?;
+                }
+            }
+        }
     }
     Ok(())
 }
 
-fn show_bytes(mut reader: Box<dyn BufRead>, bytes: usize) -> MyResult<()> {
-    let mut buf = vec![0; bytes];
-    let result = reader.read(buf.as_mut_slice());
+// ファイルが短ければ読めた分だけ出す
+fn show_bytes(mut reader: Box<dyn BufRead>, bytes: Count, filename: &str) -> MyResult<()> {
+    let mut buf = vec![];
+    let result = match bytes {
+        // 1G のような大きな指定でも先に確保しないよう、読めた分だけ溜める
+        Count::First(bytes) => reader.take(bytes as u64).read_to_end(&mut buf),
+        Count::AllButLast(bytes) => reader
+            .read_to_end(&mut buf)
+            .map(|n| n.saturating_sub(bytes)),
+    };
 
     if let Err(e) = result {
-        eprintln!("headr: error reading '{}': {}", "stdin", e);
+        let filename = if filename == "-" { "stdin" } else { filename };
+        eprintln!("headr: error reading '{}': {}", filename, e);
         return Ok(());
     }
 
@@ -64,6 +118,36 @@ fn show_bytes(mut reader: Box<dyn BufRead>, bytes: usize) -> MyResult<()> {
     Ok(())
 }
 
+fn count_lines(mut reader: impl BufRead, lines: Count, terminator: u8) -> MyResult<usize> {
+    let limit = match lines {
+        Count::First(lines) => lines,
+        Count::AllButLast(_) => usize::MAX,
+    };
+    let mut buf = vec![];
+    let mut count = 0;
+    while count < limit {
+        buf.clear();
+        if reader.read_until(terminator, &mut buf)? == 0 {
+            break;
+        }
+        count += 1;
+    }
+    match lines {
+        Count::First(_) => Ok(count),
+        Count::AllButLast(lines) => Ok(count.saturating_sub(lines)),
+    }
+}
+
+fn count_bytes(mut reader: impl Read, bytes: Count) -> MyResult<usize> {
+    let count = match bytes {
+        Count::First(bytes) => io::copy(&mut reader.take(bytes as u64), &mut io::sink())?,
+        Count::AllButLast(bytes) => {
+            io::copy(&mut reader, &mut io::sink())?.saturating_sub(bytes as u64)
+        }
+    };
+    Ok(count as usize)
+}
+
 pub fn get_args() -> MyResult<Config> {
     let matches = Command::new("headr")
         .version("0.1.0")
@@ -80,9 +164,10 @@ pub fn get_args() -> MyResult<Config> {
                 .value_name("LINES")
                 .short('n')
                 .long("lines")
-                .help("Number of lines")
+                .help("Number of lines (-N: all but the last N)")
                 .num_args(1)
-                // .value_parser(clap::value_parser!(usize))
+                .allow_hyphen_values(true)
+                .value_parser(parse_count)
                 .default_value("10"),
         )
         .arg(
@@ -90,51 +175,111 @@ pub fn get_args() -> MyResult<Config> {
                 .value_name("BYTES")
                 .short('c')
                 .long("bytes")
-                .help("Number of bytes")
+                .help("Number of bytes (-N: all but the last N)")
                 .num_args(1)
-                // .value_parser(clap::value_parser!(usize))
+                .allow_hyphen_values(true)
+                .value_parser(parse_count)
                 .conflicts_with("lines"),
         )
+        .arg(
+            Arg::new("count_only")
+                .long("count-only")
+                .action(ArgAction::SetTrue)
+                .help("Print the number of lines/bytes up to the limit instead of the contents"),
+        )
+        .arg(
+            Arg::new("keep_header")
+                .long("keep-header")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("bytes")
+                .help("Always print the first line, followed by LINES more lines"),
+        )
+        .arg(
+            Arg::new("zero_terminated")
+                .short('z')
+                .long("zero-terminated")
+                .action(ArgAction::SetTrue)
+                .help("Line delimiter is NUL, not newline"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .visible_alias("silent")
+                .action(ArgAction::SetTrue)
+                .overrides_with("verbose")
+                .help("Never print headers giving file names"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(ArgAction::SetTrue)
+                .overrides_with("quiet")
+                .help("Always print headers giving file names"),
+        )
         .get_matches();
 
-    let lines = matches
-        .get_one::<String>("lines")
-        .map(|v| parse_positive_int(v))
-        .transpose()
-        .map_err(|e| {
-            format!(
-                "error: invalid value '{}' for '--lines <LINES>': invalid digit found in string",
-                e
-            )
-        })?;
-    let bytes = matches
-        .get_one::<String>("bytes")
-        .map(|v| parse_positive_int(v))
-        .transpose()
-        .map_err(|e| {
-            format!(
-                "error: invalid value '{}' for '--bytes <BYTES>': invalid digit found in string",
-                e
-            )
-        })?;
-
     Ok(Config {
         files: matches
             .get_many::<String>("files")
             .expect("No files")
             .cloned()
             .collect(),
-        // lines: matches.get_one::<usize>("lines").expect("No lines").clone(),
-        // bytes: matches.get_one::<usize>("bytes").copied(),
-        lines: lines.unwrap_or(10),
-        bytes,
+        lines: *matches.get_one::<Count>("lines").unwrap(),
+        bytes: matches.get_one::<Count>("bytes").copied(),
+        count_only: matches.get_flag("count_only"),
+        keep_header: matches.get_flag("keep_header"),
+        zero_terminated: matches.get_flag("zero_terminated"),
+        headers: if matches.get_flag("quiet") {
+            Some(false)
+        } else if matches.get_flag("verbose") {
+            Some(true)
+        } else {
+            None
+        },
     })
 }
 
 pub fn parse_positive_int(val: &str) -> MyResult<usize> {
-    match val.parse::<usize>() {
-        Ok(n) if n > 0 => Ok(n),
-        _ => Err(val.into()),
+    Ok(parse_positive(val)?)
+}
+
+// clap にそのまま渡せるよう、エラーは理由だけの文字列にする
+fn parse_positive(val: &str) -> Result<usize, String> {
+    match parse_size(val)? {
+        0 => Err("value must be greater than 0".to_string()),
+        n => Ok(n),
+    }
+}
+
+// GNU head と同じ接尾辞を受け付ける (b=512, K=1024, KB=1000, KiB=1024, M, G も同様)
+fn parse_size(val: &str) -> Result<usize, String> {
+    let digits = val.find(|c: char| !c.is_ascii_digit()).unwrap_or(val.len());
+    let (number, suffix) = val.split_at(digits);
+    let multiplier: usize = match suffix {
+        "" => 1,
+        "b" => 512,
+        "k" | "K" | "KiB" => 1 << 10,
+        "kB" | "KB" => 1000,
+        "M" | "MiB" => 1 << 20,
+        "MB" => 1000 * 1000,
+        "G" | "GiB" => 1 << 30,
+        "GB" => 1000 * 1000 * 1000,
+        _ => return Err("invalid digit found in string".to_string()),
+    };
+    number
+        .parse::<usize>()
+        .map_err(|e| e.to_string())?
+        .checked_mul(multiplier)
+        .ok_or_else(|| "number too large to fit in target type".to_string())
+}
+
+// "-N" は末尾の N 個を除いた全部。"-0" なら全部になる
+pub fn parse_count(val: &str) -> Result<Count, String> {
+    match val.strip_prefix('-') {
+        Some(n) => parse_size(n).map(Count::AllButLast),
+        None => parse_positive(val).map(Count::First),
     }
 }
 
@@ -11,6 +11,7 @@ const ONE: &str = "./tests/inputs/one.txt";
 const TWO: &str = "./tests/inputs/two.txt";
 const THREE: &str = "./tests/inputs/three.txt";
 const TWELVE: &str = "./tests/inputs/twelve.txt";
+const PEOPLE: &str = "./tests/inputs/people.csv";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -66,6 +67,33 @@ fn dies_bad_lines() -> Result<()> {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_bad_lines_message() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "foo", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with(
+            "error: invalid value 'foo' for '--lines <LINES>': invalid digit found in string\n",
+        ));
+    Command::cargo_bin(PRG)?
+        .args(["-n", "0", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with(
+            "error: invalid value '0' for '--lines <LINES>': value must be greater than 0\n",
+        ));
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1Q", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '1Q' for '--bytes <BYTES>'",
+        ));
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn dies_bytes_and_lines() -> Result<()> {
@@ -438,3 +466,273 @@ fn test_parse_positive_int() {
     let res = headr::parse_positive_int("0");
     assert!(res.is_err());
 }
+
+#[test]
+fn test_parse_positive_int_suffix() {
+    assert_eq!(headr::parse_positive_int("1k").unwrap(), 1024);
+    assert_eq!(headr::parse_positive_int("1K").unwrap(), 1024);
+    assert_eq!(headr::parse_positive_int("1kB").unwrap(), 1000);
+    assert_eq!(headr::parse_positive_int("2M").unwrap(), 2 * 1024 * 1024);
+    assert_eq!(headr::parse_positive_int("2MB").unwrap(), 2_000_000);
+    assert_eq!(headr::parse_positive_int("1G").unwrap(), 1 << 30);
+    assert_eq!(headr::parse_positive_int("512b").unwrap(), 512 * 512);
+
+    // 知らない接尾辞や数字のないものはエラー
+    assert!(headr::parse_positive_int("1Q").is_err());
+    assert!(headr::parse_positive_int("k").is_err());
+    assert!(headr::parse_positive_int("0k").is_err());
+    assert!(headr::parse_positive_int("99999999999999999999G").is_err());
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_suffix() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1k"])
+        .write_stdin("x".repeat(2000))
+        .assert()
+        .success()
+        .stdout("x".repeat(1024));
+    Ok(())
+}
+
+#[test]
+fn test_parse_count() {
+    use headr::Count;
+
+    assert_eq!(headr::parse_count("3").unwrap(), Count::First(3));
+    assert_eq!(headr::parse_count("-2").unwrap(), Count::AllButLast(2));
+    assert_eq!(headr::parse_count("-0").unwrap(), Count::AllButLast(0));
+    assert!(headr::parse_count("0").is_err());
+    assert!(headr::parse_count("--2").is_err());
+    assert!(headr::parse_count("-foo").is_err());
+}
+
+// --------------------------------------------------
+#[test]
+fn all_but_last_lines() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-2"])
+        .write_stdin("1\n2\n3\n4\n5\n")
+        .assert()
+        .success()
+        .stdout("1\n2\n3\n");
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-2", "--count-only"])
+        .write_stdin("1\n2\n3\n4\n5\n")
+        .assert()
+        .success()
+        .stdout("3\n");
+    // 行数より多く除けば何も出さない
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-5", THREE])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn all_but_last_suffix() -> Result<()> {
+    // 1K 行より短いので何も出さない
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-1K"])
+        .write_stdin("1\n2\n3\n")
+        .assert()
+        .success()
+        .stdout("");
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-1K"])
+        .write_stdin("abc")
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn all_but_last_huge_count() -> Result<()> {
+    for count in ["-1000000000", "-18446744073709551615"] {
+        Command::cargo_bin(PRG)?
+            .args(["-n", count])
+            .write_stdin("1\n2\n3\n")
+            .assert()
+            .success()
+            .stdout("");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn all_but_last_bytes() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-3"])
+        .write_stdin("abcdef")
+        .assert()
+        .success()
+        .stdout("abc");
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-3", "--count-only"])
+        .write_stdin("abcdef")
+        .assert()
+        .success()
+        .stdout("3\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn three_n5_count_only() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([THREE, "-n", "5", "--count-only"])
+        .assert()
+        .success()
+        .stdout("3\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn twelve_count_only() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([TWELVE, "--count-only"])
+        .assert()
+        .success()
+        .stdout("10\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn count_only_bytes() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([EMPTY, THREE, "-c", "100", "--count-only"])
+        .assert()
+        .success()
+        .stdout(format!("==> {EMPTY} <==\n0\n\n==> {THREE} <==\n27\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn keep_header() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([PEOPLE, "-n", "2", "--keep-header"])
+        .assert()
+        .success()
+        .stdout("name,age\nalice,30\nbob,25\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn keep_header_huge_count() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([PEOPLE, "-n", "18446744073709551615", "--keep-header"])
+        .assert()
+        .success()
+        .stdout(fs::read_to_string(PEOPLE)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_keep_header_bytes() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([PEOPLE, "-c", "2", "--keep-header"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn verbose_single_file() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-v", "-n", "1", ONE])
+        .assert()
+        .success()
+        .stdout(format!("==> {ONE} <==\n{}", fs::read_to_string(ONE)?));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_multiple_files() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-q", "-c", "3", ONE, TWO])
+        .assert()
+        .success()
+        .stdout("ÖnTwo");
+    // 後に指定したほうが優先される
+    Command::cargo_bin(PRG)?
+        .args(["-q", "-v", "-c", "3", ONE, TWO])
+        .assert()
+        .success()
+        .stdout(format!("==> {ONE} <==\nÖn\n==> {TWO} <==\nTwo"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_longer_than_file() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "100", ONE])
+        .assert()
+        .success()
+        .stdout(fs::read_to_string(ONE)?);
+    Command::cargo_bin(PRG)?
+        .args(["-c", "100", EMPTY])
+        .assert()
+        .success()
+        .stdout("");
+    Command::cargo_bin(PRG)?
+        .args(["-c", "100"])
+        .write_stdin("short")
+        .assert()
+        .success()
+        .stdout("short");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn bytes_read_error_names_file() -> Result<()> {
+    // ディレクトリは開けるが読めない
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1", "tests/inputs"])
+        .assert()
+        .success()
+        .stderr(predicate::str::starts_with(
+            "headr: error reading 'tests/inputs': ",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-n", "2"])
+        .write_stdin("a\nb\0c\0d\0")
+        .assert()
+        .success()
+        .stdout("a\nb\0c\0");
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-n", "-1"])
+        .write_stdin("a\0b\0c")
+        .assert()
+        .success()
+        .stdout("a\0b\0");
+    Command::cargo_bin(PRG)?
+        .args(["-z", "--count-only"])
+        .write_stdin("a\nb\0c\0d\0")
+        .assert()
+        .success()
+        .stdout("3\n");
+    Ok(())
+}
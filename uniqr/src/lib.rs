@@ -12,6 +12,24 @@ pub struct Config {
     in_file: String,
     out_file: Option<String>,
     count: bool,
+    count_only: bool,
+    numeric: bool,
+    strict_numeric: bool,
+    min_count: usize,
+    record_sep: Option<String>,
+    skip_fields: usize,
+    skip_chars: usize,
+    check_chars: Option<usize>,
+    group: Option<Group>,
+}
+
+// --group で空行を入れる位置
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Group {
+    Separate,
+    Prepend,
+    Append,
+    Both,
 }
 
 pub fn run(config: Config) -> MyResult<()> {
@@ -23,36 +41,61 @@ pub fn run(config: Config) -> MyResult<()> {
     let mut line = String::new();
     let mut before = String::new();
     let mut count: usize = 0;
-
-    let mut write = |count: usize, text: &str| -> MyResult<()> {
-        if count > 0 {
-            if config.count {
-                write!(out, "{:4} {}", count, text)?;
-            } else {
-                write!(out, "{}", text)?;
-            }
-        }
-
-        Ok(())
-    };
+    let mut groups: usize = 0;
 
     loop {
-        let bytes = file.read_line(&mut line)?;
+        let bytes = read_record(&mut file, &mut line, config.record_sep.as_deref())?;
         if bytes == 0 {
             break;
         }
 
-        // 違うやつが来た
-        if line.trim_end() != before.trim_end() {
-            write(count, &before)?;
+        // 違うやつが来た。最初の行は比較キーが空でも before に入れるが、
+        // --strict-numeric の検査のために自分自身とは比べる
+        let new_group = if count == 0 {
+            is_same(&line, &line, &config)?;
+            true
+        } else {
+            !is_same(&line, &before, &config)?
+        };
+        if new_group {
+            match config.group {
+                None => write_group(&mut out, count, &before, &config)?,
+                Some(Group::Prepend | Group::Both) => writeln!(out)?,
+                Some(_) if groups > 0 => writeln!(out)?,
+                Some(_) => {}
+            }
+            groups += 1;
             before = line.clone();
             count = 0;
         }
+        // --group ではすべての行をそのまま出す
+        if config.group.is_some() {
+            write!(out, "{}", line)?;
+        }
         count += 1;
         line.clear();
     }
 
-    write(count, &before)?;
+    match config.group {
+        None => write_group(&mut out, count, &before, &config)?,
+        Some(Group::Append | Group::Both) if groups > 0 => writeln!(out)?,
+        Some(_) => {}
+    }
+
+    Ok(())
+}
+
+fn write_group(out: &mut impl Write, count: usize, text: &str, config: &Config) -> MyResult<()> {
+    // --min-count に満たないグループは出力しない
+    if count > 0 && count >= config.min_count {
+        if config.count_only {
+            writeln!(out, "{}", count)?;
+        } else if config.count {
+            write!(out, "{:4} {}", count, text)?;
+        } else {
+            write!(out, "{}", text)?;
+        }
+    }
 
     Ok(())
 }
@@ -82,17 +125,177 @@ pub fn get_args() -> MyResult<Config> {
                 .action(ArgAction::SetTrue)
                 .help("Show counts"),
         )
+        .arg(
+            Arg::new("count_only")
+                .long("count-only")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("count")
+                .help("Show only the counts, without the lines"),
+        )
+        .arg(
+            Arg::new("numeric")
+                .long("numeric")
+                .action(ArgAction::SetTrue)
+                .help("Compare lines by their numeric value"),
+        )
+        .arg(
+            Arg::new("strict_numeric")
+                .long("strict-numeric")
+                .action(ArgAction::SetTrue)
+                .requires("numeric")
+                .help("Fail on non-numeric lines instead of comparing them as text"),
+        )
+        .arg(
+            Arg::new("min_count")
+                .value_name("N")
+                .long("min-count")
+                .num_args(1)
+                .default_value("1")
+                .value_parser(clap::value_parser!(usize))
+                .help("Only print groups that occur at least N times"),
+        )
+        .arg(
+            Arg::new("record_sep")
+                .value_name("SEP")
+                .long("record-sep")
+                .num_args(1)
+                .help("Treat lines up to a line equal to SEP as one record ('' for paragraphs)"),
+        )
+        .arg(
+            Arg::new("skip_fields")
+                .value_name("N")
+                .short('f')
+                .long("skip-fields")
+                .num_args(1)
+                .default_value("0")
+                .value_parser(clap::value_parser!(usize))
+                .help("Avoid comparing the first N fields"),
+        )
+        .arg(
+            Arg::new("skip_chars")
+                .value_name("N")
+                .short('s')
+                .long("skip-chars")
+                .num_args(1)
+                .default_value("0")
+                .value_parser(clap::value_parser!(usize))
+                .help("Avoid comparing the first N characters"),
+        )
+        .arg(
+            Arg::new("check_chars")
+                .value_name("N")
+                .short('w')
+                .long("check-chars")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize))
+                .help("Compare no more than N characters in lines"),
+        )
+        .arg(
+            Arg::new("group")
+                .value_name("METHOD")
+                .long("group")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("separate")
+                .value_parser(["separate", "prepend", "append", "both"])
+                .conflicts_with_all(["count", "count_only", "min_count"])
+                .help("Show all lines, separating groups with an empty line"),
+        )
         .get_matches();
 
     let in_file = matches.get_one::<String>("in_file").unwrap().to_string();
     let out_file = matches.get_one::<String>("out_file").map(String::from);
     let count = matches.get_flag("count");
+    let count_only = matches.get_flag("count_only");
+    let numeric = matches.get_flag("numeric");
+    let strict_numeric = matches.get_flag("strict_numeric");
+    let min_count = *matches.get_one::<usize>("min_count").unwrap();
+    let record_sep = matches.get_one::<String>("record_sep").cloned();
+    let skip_fields = *matches.get_one::<usize>("skip_fields").unwrap();
+    let skip_chars = *matches.get_one::<usize>("skip_chars").unwrap();
+    let check_chars = matches.get_one::<usize>("check_chars").copied();
+    let group = matches
+        .get_one::<String>("group")
+        .map(|method| match method.as_str() {
+            "prepend" => Group::Prepend,
+            "append" => Group::Append,
+            "both" => Group::Both,
+            _ => Group::Separate,
+        });
 
-    return Ok(Config {
+    Ok(Config {
         in_file,
         out_file,
         count,
-    });
+        count_only,
+        numeric,
+        strict_numeric,
+        min_count,
+        record_sep,
+        skip_fields,
+        skip_chars,
+        check_chars,
+        group,
+    })
+}
+
+// 区切りの行までをひとつのレコードとして buf に追加する。区切りの行もレコードに含める
+fn read_record(file: &mut impl BufRead, buf: &mut String, sep: Option<&str>) -> MyResult<usize> {
+    let sep = match sep {
+        None => return Ok(file.read_line(buf)?),
+        Some(sep) => sep,
+    };
+    let mut total = 0;
+    loop {
+        let start = buf.len();
+        let bytes = file.read_line(buf)?;
+        total += bytes;
+        if bytes == 0 || buf[start..].trim_end_matches(['\r', '\n']) == sep {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+// 比較に使う部分を取り出す。出力するのは元の行のまま
+fn compare_key<'a>(line: &'a str, config: &Config) -> &'a str {
+    let key = skip_fields(line.trim_end(), config.skip_fields);
+    // N 文字に満たない行は空として比べる
+    let key = match key.char_indices().nth(config.skip_chars) {
+        Some((i, _)) => &key[i..],
+        None => "",
+    };
+    // -w は読み飛ばした後の先頭 N 文字だけを比べる
+    match config.check_chars.and_then(|n| key.char_indices().nth(n)) {
+        Some((i, _)) => &key[..i],
+        None => key,
+    }
+}
+
+// uniq -f と同じく、フィールドは空白の並びとそれに続く空白以外の並び
+fn skip_fields(line: &str, n: usize) -> &str {
+    let mut rest = line;
+    for _ in 0..n {
+        rest = rest.trim_start_matches([' ', '\t']);
+        rest = rest.trim_start_matches(|c: char| c != ' ' && c != '\t');
+    }
+    rest
+}
+
+fn is_same(line: &str, before: &str, config: &Config) -> MyResult<bool> {
+    let (line, before) = (compare_key(line, config), compare_key(before, config));
+    if config.numeric {
+        match (line.trim().parse::<f64>(), before.trim().parse::<f64>()) {
+            // nan 同士のように数値としては等しくならなくても、同じ文字列なら同じ行
+            (Ok(a), Ok(b)) => return Ok(a == b || line == before),
+            (Err(_), _) if config.strict_numeric => {
+                return Err(format!("not a number: \"{}\"", line).into())
+            }
+            // 数値でない行は文字列として比較する
+            _ => {}
+        }
+    }
+    Ok(line == before)
 }
 
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
@@ -616,3 +616,259 @@ fn t6_outfile_count() -> Result<()> {
 fn t6_stdin_outfile_count() -> Result<()> {
     run_stdin_outfile_count(&T6)
 }
+
+// --------------------------------------------------
+#[test]
+fn numeric() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--numeric", "-c"])
+        .write_stdin("1\n1.0\n01\n2\nfoo\nfoo\n2.5\n")
+        .assert()
+        .success()
+        .stdout("   3 1\n   1 2\n   2 foo\n   1 2.5\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn numeric_nan() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--numeric", "-c"])
+        .write_stdin("nan\nnan\nNaN\n")
+        .assert()
+        .success()
+        .stdout("   2 nan\n   1 NaN\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn not_numeric_by_default() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .write_stdin("1\n1.0\n")
+        .assert()
+        .success()
+        .stdout("1\n1.0\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_strict_numeric() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--numeric", "--strict-numeric"])
+        .write_stdin("1\nfoo\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"not a number: "foo""#));
+    Command::cargo_bin(PRG)?
+        .args(["--numeric", "--strict-numeric"])
+        .write_stdin("x\n1\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"not a number: "x""#));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn min_count() -> Result<()> {
+    let input = "a\nb\nb\nc\nc\nc\nd\nd\nd\nd\nb\n";
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--min-count", "3"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("   3 c\n   4 d\n");
+    Command::cargo_bin(PRG)?
+        .args(["--min-count", "2"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("b\nc\nd\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_min_count() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--min-count", "x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'x'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn count_only() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .arg("--count-only")
+        .write_stdin("a\nb\nb\nc\nc\nc\na\n")
+        .assert()
+        .success()
+        .stdout("1\n2\n3\n1\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn count_only_min_count() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--count-only", "--min-count", "2"])
+        .write_stdin("a\nb\nb\nc\nc\nc\na\n")
+        .assert()
+        .success()
+        .stdout("2\n3\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn record_sep_paragraphs() -> Result<()> {
+    let input = "first line\nsecond line\n\nfirst line\nsecond line\n\nother\n";
+    Command::cargo_bin(PRG)?
+        .args(["--record-sep", ""])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("first line\nsecond line\n\nother\n");
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--record-sep", "%%"])
+        .write_stdin("a\nb\n%%\na\nb\n%%\n")
+        .assert()
+        .success()
+        .stdout("   2 a\nb\n%%\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skip_fields() -> Result<()> {
+    let input = "1 apple\n2 apple\n3  apple\n4 banana\n5 apple\n";
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--skip-fields", "1"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("   2 1 apple\n   1 3  apple\n   1 4 banana\n   1 5 apple\n");
+    Command::cargo_bin(PRG)?
+        .args(["-f", "2"])
+        .write_stdin("a x\nb x\nc\n")
+        .assert()
+        .success()
+        .stdout("a x\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skip_chars() -> Result<()> {
+    let input = "\
+[12:00:01] started
+[12:00:02] started
+[12:00:03] stopped
+[12:00:04] started
+";
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--skip-chars", "11"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("   2 [12:00:01] started\n   1 [12:00:03] stopped\n   1 [12:00:04] started\n");
+    // 短い行は空として比べる
+    Command::cargo_bin(PRG)?
+        .args(["-s", "3"])
+        .write_stdin("ab\n\nxyz\nabcd\n")
+        .assert()
+        .success()
+        .stdout("ab\nabcd\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skip_fields_and_chars() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "1", "-s", "2"])
+        .write_stdin("1 xapple\n2 yapple\n3 xbanana\n")
+        .assert()
+        .success()
+        .stdout("1 xapple\n3 xbanana\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn group() -> Result<()> {
+    let input = "a\na\nb\nc\nc\n";
+    Command::cargo_bin(PRG)?
+        .arg("--group")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("a\na\n\nb\n\nc\nc\n");
+    Command::cargo_bin(PRG)?
+        .arg("--group=separate")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("a\na\n\nb\n\nc\nc\n");
+    Command::cargo_bin(PRG)?
+        .arg("--group")
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn group_methods() -> Result<()> {
+    let input = "a\na\nb\n";
+    for (method, expected) in [
+        ("prepend", "\na\na\n\nb\n"),
+        ("append", "a\na\n\nb\n\n"),
+        ("both", "\na\na\n\nb\n\n"),
+    ] {
+        Command::cargo_bin(PRG)?
+            .arg(format!("--group={}", method))
+            .write_stdin(input)
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_group_count() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--group", "-c"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn check_chars() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--check-chars", "3"])
+        .write_stdin("foobar\nfoobaz\nfoo\nfob\n")
+        .assert()
+        .success()
+        .stdout("   3 foobar\n   1 fob\n");
+    // -f と -s で読み飛ばした後の文字で比べる
+    Command::cargo_bin(PRG)?
+        .args(["-f", "1", "-s", "2", "-w", "2"])
+        .write_stdin("1 xab1\n2 yab2\n3 zac3\n")
+        .assert()
+        .success()
+        .stdout("1 xab1\n3 zac3\n");
+    Ok(())
+}
@@ -2,26 +2,62 @@ use clap::{Arg, ArgAction, Command};
 use std::{
     error::Error,
     fs::File,
-    io::{self, BufRead, BufReader},
+    io::{self, BufRead, BufReader, Write},
 };
+use unicode_segmentation::UnicodeSegmentation;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+// --total で合計行をいつ出すか
+#[derive(Debug, PartialEq)]
+pub enum Total {
+    Auto,
+    Only,
+    Never,
+}
+
 #[derive(Debug)]
 pub struct Config {
-    files: Vec<String>,
-    lines: bool,
-    words: bool,
-    bytes: bool,
-    chars: bool,
+    pub files: Vec<String>,
+    pub lines: bool,
+    pub words: bool,
+    pub bytes: bool,
+    pub chars: bool,
+    pub fields: bool,
+    pub delimiter: String,
+    pub no_blank_lines: bool,
+    pub json_lines: bool,
+    pub graphemes: bool,
+    pub total: Total,
+}
+
+impl Config {
+    // ライブラリとして使う場合の入口。wc と同じく行数・単語数・バイト数を数える。
+    // ほかのオプションが必要ならフィールドを直接変更する
+    pub fn new(files: Vec<String>) -> Config {
+        Config {
+            files,
+            lines: true,
+            words: true,
+            bytes: true,
+            chars: false,
+            fields: false,
+            delimiter: "\t".to_string(),
+            no_blank_lines: false,
+            json_lines: false,
+            graphemes: false,
+            total: Total::Auto,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct FileInfo {
-    num_lines: usize,
-    num_words: usize,
-    num_bytes: usize,
-    num_chars: usize,
+    pub num_lines: usize,
+    pub num_words: usize,
+    pub num_bytes: usize,
+    pub num_chars: usize,
+    pub num_fields: usize,
 }
 
 pub fn run(config: Config) -> MyResult<()> {
@@ -30,46 +66,123 @@ pub fn run(config: Config) -> MyResult<()> {
         num_words: 0,
         num_bytes: 0,
         num_chars: 0,
+        num_fields: 0,
     };
+    // 桁幅を揃えるため、JSON Lines 以外は全部数えてから出す
+    let mut infos = vec![];
     for filename in &config.files {
         match open(filename) {
             Err(e) => eprintln!("{}: {}", filename, e),
             Ok(file) => {
-                let info = count(file);
+                // 行数とバイト数だけなら UTF-8 として読まずに数える
+                let info = if config.words || config.chars || config.fields || config.no_blank_lines
+                {
+                    count(
+                        file,
+                        config.no_blank_lines,
+                        config.graphemes,
+                        &config.delimiter,
+                    )
+                } else {
+                    count_bytes(file)
+                };
 
                 match info {
                     Err(e) => eprintln!("{}: {}", filename, e),
                     Ok(info) => {
-                        print_info(&info, &config, filename);
+                        if config.json_lines && config.total != Total::Only {
+                            print_json_line(&info, &config, Some(filename))?;
+                        }
 
                         total_info.num_lines += info.num_lines;
                         total_info.num_words += info.num_words;
                         total_info.num_bytes += info.num_bytes;
                         total_info.num_chars += info.num_chars;
+                        total_info.num_fields += info.num_fields;
+                        if config.total != Total::Only {
+                            infos.push((filename.as_str(), info));
+                        }
                     }
                 }
             }
         }
     }
-    if config.files.len() > 1 {
-        print_info(&total_info, &config, "total");
+    if config.json_lines {
+        // JSON Lines では合計は (--total=never でなければ) 常に最後の 1 行として出す
+        if config.total != Total::Never {
+            print_json_line(&total_info, &config, None)?;
+        }
+        return Ok(());
+    }
+
+    match config.total {
+        Total::Auto if config.files.len() > 1 => infos.push(("total", total_info)),
+        // GNU wc と同じく合計だけのときは名前を付けない
+        Total::Only => infos.push(("-", total_info)),
+        _ => {}
+    }
+    // GNU wc と同じく、いちばん大きい数の桁数に右揃えする
+    let width = infos
+        .iter()
+        .flat_map(|(_, info)| selected_counts(info, &config))
+        .map(|n| n.to_string().len())
+        .max()
+        .unwrap_or(1);
+    for (filename, info) in &infos {
+        print_info(info, &config, filename, width);
     }
     Ok(())
 }
 
-fn print_info(info: &FileInfo, config: &Config, filename: &str) {
+// 表示する列の値を順に返す
+fn selected_counts(info: &FileInfo, config: &Config) -> Vec<usize> {
+    [
+        (config.lines, info.num_lines),
+        (config.words, info.num_words),
+        (config.bytes, info.num_bytes),
+        (config.chars, info.num_chars),
+        (config.fields, info.num_fields),
+    ]
+    .into_iter()
+    .filter_map(|(selected, n)| selected.then_some(n))
+    .collect()
+}
+
+fn print_json_line(info: &FileInfo, config: &Config, filename: Option<&str>) -> MyResult<()> {
+    let mut value = serde_json::Map::new();
+    match filename {
+        Some(filename) => value.insert("file".to_string(), filename.into()),
+        None => value.insert("total".to_string(), true.into()),
+    };
     if config.lines {
-        print!("{:8}", info.num_lines);
+        value.insert("lines".to_string(), info.num_lines.into());
     }
     if config.words {
-        print!("{:8}", info.num_words);
+        value.insert("words".to_string(), info.num_words.into());
     }
     if config.bytes {
-        print!("{:8}", info.num_bytes);
+        value.insert("bytes".to_string(), info.num_bytes.into());
     }
     if config.chars {
-        print!("{:8}", info.num_chars);
+        value.insert("chars".to_string(), info.num_chars.into());
+    }
+    if config.fields {
+        value.insert("fields".to_string(), info.num_fields.into());
     }
+
+    // 1 ファイルごとにすぐ流したいので都度 flush する
+    let mut out = io::stdout().lock();
+    writeln!(out, "{}", serde_json::Value::Object(value))?;
+    out.flush()?;
+    Ok(())
+}
+
+fn print_info(info: &FileInfo, config: &Config, filename: &str, width: usize) {
+    let columns = selected_counts(info, config)
+        .iter()
+        .map(|n| format!("{:width$}", n))
+        .collect::<Vec<_>>();
+    print!("{}", columns.join(" "));
     if filename == "-" {
         println!();
     } else {
@@ -77,11 +190,17 @@ fn print_info(info: &FileInfo, config: &Config, filename: &str) {
     }
 }
 
-pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
+pub fn count(
+    mut file: impl BufRead,
+    no_blank_lines: bool,
+    graphemes: bool,
+    delimiter: &str,
+) -> MyResult<FileInfo> {
     let mut num_lines = 0;
     let mut num_words = 0;
     let mut num_bytes = 0;
     let mut num_chars = 0;
+    let mut num_fields = 0;
 
     let mut line = String::new();
 
@@ -92,10 +211,25 @@ pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
             break;
         }
 
+        num_bytes += line.len();
+        // 空行(空白のみの行)はバイト数以外に数えない
+        if no_blank_lines && line.trim().is_empty() {
+            continue;
+        }
+
         num_lines += 1;
         num_words += line.split_whitespace().count();
-        num_bytes += line.len();
-        num_chars += line.chars().count();
+        // --graphemes なら見た目の文字 (書記素クラスタ) で数える
+        num_chars += if graphemes {
+            line.graphemes(true).count()
+        } else {
+            line.chars().count()
+        };
+        // 空行はフィールドなしとして数える
+        let text = line.trim_end_matches(['\r', '\n']);
+        if !text.is_empty() {
+            num_fields += text.split(delimiter).count();
+        }
     }
 
     Ok(FileInfo {
@@ -103,6 +237,32 @@ pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
         num_words,
         num_bytes,
         num_chars,
+        num_fields,
+    })
+}
+
+// 改行とバイトだけを数える。UTF-8 でない (バイナリの) ファイルでも数えられる
+pub fn count_bytes(mut file: impl BufRead) -> MyResult<FileInfo> {
+    let mut num_lines = 0;
+    let mut num_bytes = 0;
+
+    let mut line = vec![];
+    loop {
+        line.clear();
+        let n = file.read_until(b'\n', &mut line)?;
+        if n == 0 {
+            break;
+        }
+        num_bytes += n;
+        num_lines += 1;
+    }
+
+    Ok(FileInfo {
+        num_lines,
+        num_words: 0,
+        num_bytes,
+        num_chars: 0,
+        num_fields: 0,
     })
 }
 
@@ -151,20 +311,79 @@ pub fn get_args() -> MyResult<Config> {
                 .conflicts_with("bytes")
                 .help("Show character count"),
         )
+        .arg(
+            Arg::new("graphemes")
+                .long("graphemes")
+                .action(ArgAction::SetTrue)
+                .requires("chars")
+                .help("Count characters as grapheme clusters"),
+        )
+        .arg(
+            Arg::new("fields")
+                .long("fields")
+                .action(ArgAction::SetTrue)
+                .help("Show field count"),
+        )
+        .arg(
+            Arg::new("delimiter")
+                .value_name("DELIMITER")
+                .short('d')
+                .long("delimiter")
+                .default_value("\t")
+                .requires("fields")
+                .help("Field delimiter for --fields"),
+        )
+        .arg(
+            Arg::new("no_blank_lines")
+                .long("no-blank-lines")
+                .action(ArgAction::SetTrue)
+                .help("Do not count blank lines"),
+        )
+        .arg(
+            Arg::new("files0_from")
+                .value_name("FILE")
+                .long("files0-from")
+                .num_args(1)
+                .help("Read NUL-separated input file names from FILE (- for stdin)"),
+        )
+        .arg(
+            Arg::new("total")
+                .value_name("WHEN")
+                .long("total")
+                .num_args(1)
+                .value_parser(["auto", "only", "never"])
+                .default_value("auto")
+                .help("When to print the total: auto (more than one file), only, never"),
+        )
+        .arg(
+            Arg::new("json_lines")
+                .long("json-lines")
+                .action(ArgAction::SetTrue)
+                .help("Print one JSON object per file, followed by the total"),
+        )
         .get_matches();
 
-    let files = matches
-        .get_many::<String>("files")
-        .unwrap()
-        .map(|s| s.to_string())
-        .collect();
+    // --files0-from があれば位置引数のファイルは使わない
+    let files = match matches.get_one::<String>("files0_from") {
+        Some(list) => read_files0(list).map_err(|e| format!("{}: {}", list, e))?,
+        None => matches
+            .get_many::<String>("files")
+            .unwrap()
+            .map(|s| s.to_string())
+            .collect(),
+    };
 
     let mut lines = matches.get_flag("lines");
     let mut words = matches.get_flag("words");
     let mut bytes = matches.get_flag("bytes");
     let chars = matches.get_flag("chars");
+    let fields = matches.get_flag("fields");
+    let delimiter = matches.get_one::<String>("delimiter").unwrap().to_string();
+    if delimiter.is_empty() {
+        return Err("--delimiter must not be empty".into());
+    }
 
-    if [lines, words, bytes, chars].iter().all(|v| !v) {
+    if [lines, words, bytes, chars, fields].iter().all(|v| !v) {
         // 全部 false ならデフォルトをセット
         lines = true;
         words = true;
@@ -177,9 +396,31 @@ pub fn get_args() -> MyResult<Config> {
         words,
         bytes,
         chars,
+        fields,
+        delimiter,
+        no_blank_lines: matches.get_flag("no_blank_lines"),
+        json_lines: matches.get_flag("json_lines"),
+        graphemes: matches.get_flag("graphemes"),
+        total: match matches.get_one::<String>("total").unwrap().as_str() {
+            "only" => Total::Only,
+            "never" => Total::Never,
+            _ => Total::Auto,
+        },
     })
 }
 
+// NUL 区切りのファイル名一覧を読む。空の名前 (末尾の NUL の後など) は飛ばす
+fn read_files0(list: &str) -> MyResult<Vec<String>> {
+    let mut files = vec![];
+    for name in open(list)?.split(b'\0') {
+        let name = String::from_utf8(name?)?;
+        if !name.is_empty() {
+            files.push(name);
+        }
+    }
+    Ok(files)
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
@@ -189,21 +430,72 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
 
 #[cfg(test)]
 mod tests {
-    use super::{count, FileInfo};
+    use super::{count, count_bytes, FileInfo};
 
     use std::io::Cursor;
 
     #[test]
     fn test_count() {
         let text = "I don't want the world. I just want your half.\r\n";
-        let info = count(Cursor::new(text));
+        let info = count(Cursor::new(text), false, false, "\t");
         assert!(info.is_ok());
         let expected = FileInfo {
             num_lines: 1,
             num_words: 10,
             num_chars: 48,
             num_bytes: 48,
+            num_fields: 1,
         };
         assert_eq!(info.unwrap(), expected);
     }
+
+    #[test]
+    fn test_count_no_blank_lines() {
+        let text = "a\n\nb\n \t\n";
+        let info = count(Cursor::new(text), false, false, "\t").unwrap();
+        assert_eq!(info.num_lines, 4);
+
+        // 空行と空白だけの行は数えない
+        let info = count(Cursor::new(text), true, false, "\t").unwrap();
+        let expected = FileInfo {
+            num_lines: 2,
+            num_words: 2,
+            num_chars: 4,
+            num_bytes: 8,
+            num_fields: 2,
+        };
+        assert_eq!(info, expected);
+    }
+
+    #[test]
+    fn test_count_fields() {
+        // 行ごとのフィールド数がばらばらでも合計する
+        let text = "a,b,c\r\nd,e\n\nf,,g,h\n";
+        let info = count(Cursor::new(text), false, false, ",").unwrap();
+        assert_eq!(info.num_fields, 9);
+
+        let info = count(Cursor::new(text), false, false, "::").unwrap();
+        assert_eq!(info.num_fields, 3);
+    }
+
+    #[test]
+    fn test_count_graphemes() {
+        // 肌の色の修飾子付きの絵文字と、結合文字付きの e
+        let text = "\u{1F44D}\u{1F3FD} e\u{301}\n";
+        let info = count(Cursor::new(text), false, false, "\t").unwrap();
+        assert_eq!(info.num_chars, 6);
+
+        let info = count(Cursor::new(text), false, true, "\t").unwrap();
+        assert_eq!(info.num_chars, 4);
+        assert_eq!(info.num_bytes, 13);
+    }
+
+    #[test]
+    fn test_count_bytes() {
+        // UTF-8 として読めないバイトがあっても最後まで数える
+        let text: &[u8] = b"ab\xff\xfe\n\x00\x80\ncd";
+        let info = count_bytes(Cursor::new(text)).unwrap();
+        assert_eq!(info.num_lines, 3);
+        assert_eq!(info.num_bytes, 10);
+    }
 }
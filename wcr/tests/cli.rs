@@ -216,3 +216,150 @@ fn test_all_words_lines() -> Result<()> {
 fn test_all_bytes_lines() -> Result<()> {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn no_blank_lines() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--no-blank-lines"])
+        .write_stdin("a\n\nb\n")
+        .assert()
+        .success()
+        .stdout("2\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fields() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--fields", "-d", ",", "tests/inputs/ragged.csv"])
+        .assert()
+        .success()
+        .stdout("12 tests/inputs/ragged.csv\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fields_lines_total() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--fields", "-d", ",", "tests/inputs/ragged.csv", "-"])
+        .write_stdin("a,b\n")
+        .assert()
+        .success()
+        .stdout(" 4 12 tests/inputs/ragged.csv\n 1  2\n 5 14 total\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn aligns_to_widest_count() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-", FOX])
+        .write_stdin("\n".repeat(100_000))
+        .assert()
+        .success()
+        .stdout("100000\n     1 tests/inputs/fox.txt\n100001 total\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from() -> Result<()> {
+    // 位置引数のファイルは無視される
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", "-", EMPTY])
+        .write_stdin(format!("{FOX}\0{ATLAMAL}\0"))
+        .assert()
+        .success()
+        .stdout(
+            "  1   9  48 tests/inputs/fox.txt\n  \
+             4  29 177 tests/inputs/atlamal.txt\n  \
+             5  38 225 total\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_files0_from() -> Result<()> {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", &bad])
+        .assert()
+        .failure()
+        .stderr(predicate::str::is_match(format!(
+            "{bad}: .* [(]os error 2[)]"
+        ))?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_invalid_utf8() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-c"])
+        .write_stdin(&b"ab\xff\xfe\n\x00\x80\ncd"[..])
+        .assert()
+        .success()
+        .stdout(" 3 10\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn total_modes() -> Result<()> {
+    let files = [EMPTY, FOX, ATLAMAL];
+    run(
+        &[&["--total", "auto"], &files[..]].concat(),
+        "tests/expected/all.out",
+    )?;
+    Command::cargo_bin(PRG)?
+        .args(["--total", "only"])
+        .args(files)
+        .assert()
+        .success()
+        .stdout("  5  38 225\n");
+    Command::cargo_bin(PRG)?
+        .args(["--total", "never"])
+        .args(files)
+        .assert()
+        .success()
+        .stdout(
+            "  0   0   0 tests/inputs/empty.txt\n  \
+             1   9  48 tests/inputs/fox.txt\n  \
+             4  29 177 tests/inputs/atlamal.txt\n",
+        );
+    // 1 ファイルでも only なら合計を出す
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--total", "only", FOX])
+        .assert()
+        .success()
+        .stdout("1\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn json_lines() -> Result<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(["-l", "--json-lines", ATLAMAL, FOX])
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let values = stdout
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    assert_eq!(
+        values,
+        [
+            serde_json::json!({"file": ATLAMAL, "lines": 4}),
+            serde_json::json!({"file": FOX, "lines": 1}),
+            serde_json::json!({"total": true, "lines": 5}),
+        ]
+    );
+    Ok(())
+}